@@ -1,7 +1,6 @@
 use std::collections::HashMap;
 use macroquad::math::Rect;
-use macroquad::prelude::{draw_texture_ex, DrawTextureParams, Texture2D, Vec2, WHITE};
-use crate::util::get_sprite_scale;
+use macroquad::prelude::{draw_texture_ex, Color, DrawTextureParams, Texture2D, Vec2, WHITE};
 
 #[derive(Clone, Debug)]
 pub struct Animation {
@@ -20,6 +19,8 @@ pub struct Animator {
     pub current_frame: usize,
     pub timer: f32,
     pub playing: bool,
+    // multiplied with the texture, white draws it as is
+    pub tint: Color,
 }
 
 impl Animator {
@@ -32,6 +33,7 @@ impl Animator {
             current_frame: 0,
             timer: 0.0,
             playing: true,
+            tint: WHITE,
         }
     }
 
@@ -86,24 +88,18 @@ impl Animator {
         }
     }
 
-    pub fn draw(&self, position: Vec2, rotation: Option<f32>, override_scale: Option<Vec2>) {
+    pub fn draw(&self, position: Vec2, rotation: Option<f32>, scale: Vec2) {
         if let Some(anim) = self.animations.get(&self.current_animation) {
             let frame_index = anim.start_frame + self.current_frame;
             let cols = (self.texture.width() / self.frame_size.x) as usize;
             let frame_x = (frame_index % cols) as f32 * self.frame_size.x;
             let frame_y = (frame_index / cols) as f32 * self.frame_size.y;
 
-            let scale = if let Some(scale) = override_scale {
-                scale
-            } else {
-                get_sprite_scale()
-            };
-
             draw_texture_ex(
                 &self.texture,
                 position.x,
                 position.y,
-                WHITE,
+                self.tint,
                 DrawTextureParams {
                     source: Some(Rect {
                         x: frame_x,
@@ -1,14 +1,20 @@
 use macroquad::prelude::*;
+use crate::warn;
+use crate::world::rock_type::{RockTypes, ROCKS_FILE};
 
 const FONT_BYTES: &[u8] = include_bytes!("../assets/fonts/PressStart2P-Regular.ttf");
 const PLAYER_SHEET_BYTES: &[u8] = include_bytes!("../assets/sprites/monke2.png");
 const ROCK_SHEET_BYTES: &[u8] = include_bytes!("../assets/sprites/Rocks floor and decor.png");
+const BANANA_BYTES: &[u8] = include_bytes!("../assets/sprites/banana.png");
 
 #[derive(Clone)]
 pub struct GlobalAssets {
     pub font: Font,
     pub rock_sprite: Texture2D,
     pub player_sprite: Texture2D,
+    pub banana_sprite: Texture2D,
+    // the kinds of rock, which are frames of the rock sheet
+    pub rocks: RockTypes,
 }
 
 impl GlobalAssets {
@@ -32,11 +38,38 @@ impl GlobalAssets {
         let player_sprite = Texture2D::from_file_with_format(PLAYER_SHEET_BYTES, Some(ImageFormat::Png));
         player_sprite.set_filter(FilterMode::Nearest);
 
+        let banana_sprite = Texture2D::from_file_with_format(BANANA_BYTES, Some(ImageFormat::Png));
+        banana_sprite.set_filter(FilterMode::Nearest);
+
+        let rocks = match RockTypes::from_json(ROCKS_FILE, &rock_sprite) {
+            Ok((rocks, errors)) => {
+                for e in errors {
+                    warn!("Skipped rock: {}", e);
+                }
+                rocks
+            }
+            Err(e) => return Err(format!("Failed to load rocks: {}", e)),
+        };
+
         Ok(Self {
             font,
             rock_sprite,
             player_sprite,
+            banana_sprite,
+            rocks,
         })
     }
 
+    /// The texture for an image used by a Tiled map or the item catalog, matched by file name
+    /// so they can point at the sprite folder however they like
+    pub fn tileset_texture(&self, image: &str) -> Option<Texture2D> {
+        let file_name = image.rsplit(['/', '\\']).next().unwrap_or(image);
+        match file_name {
+            "Rocks floor and decor.png" => Some(self.rock_sprite.clone()),
+            "monke2.png" => Some(self.player_sprite.clone()),
+            "banana.png" => Some(self.banana_sprite.clone()),
+            _ => None,
+        }
+    }
+
 }
\ No newline at end of file
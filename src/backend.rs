@@ -0,0 +1,308 @@
+#[cfg(test)]
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use macroquad::input::{KeyCode, MouseButton};
+use macroquad::math::{vec2, Vec2};
+use macroquad::window::{screen_height, screen_width};
+use crate::gamepad::{self, GamepadAxis, GamepadButton};
+use crate::replay;
+
+/// Where gameplay code gets the screen size, keyboard, mouse, gamepad and frame clock from.
+/// The game runs on a `WindowBackend`; a `HeadlessBackend` lets world and player logic
+/// run without a window, so it can be stepped tick by tick and checked in tests.
+pub trait Backend {
+    fn screen_size(&self) -> Vec2;
+    fn mouse_position(&self) -> Vec2;
+    /// Which way the mouse wheel turned this frame, 1.0 up, -1.0 down or 0.0
+    fn mouse_wheel(&self) -> f32;
+    /// Characters typed since the last call, for text boxes
+    fn typed_chars(&mut self) -> Vec<char>;
+    /// How long has passed since the last call, which is how long the next frame simulates
+    fn frame_time(&mut self) -> Duration;
+
+    fn is_key_down(&self, key: KeyCode) -> bool;
+    /// Whether the key went down this frame
+    fn is_key_pressed(&self, key: KeyCode) -> bool;
+    /// Whether the key came up this frame
+    fn is_key_released(&self, key: KeyCode) -> bool;
+    fn keys_down(&self) -> Vec<KeyCode>;
+    /// Every key that went down this frame
+    fn keys_pressed(&self) -> Vec<KeyCode>;
+
+    fn is_mouse_button_down(&self, button: MouseButton) -> bool;
+    fn is_mouse_button_pressed(&self, button: MouseButton) -> bool;
+    fn is_mouse_button_released(&self, button: MouseButton) -> bool;
+
+    fn is_gamepad_connected(&self) -> bool;
+    fn is_gamepad_button_down(&self, button: GamepadButton) -> bool;
+    fn is_gamepad_button_pressed(&self, button: GamepadButton) -> bool;
+    fn is_gamepad_button_released(&self, button: GamepadButton) -> bool;
+    /// Where an axis is now, see `GamepadAxis` for the ranges
+    fn gamepad_axis(&self, axis: GamepadAxis) -> f32;
+    /// Where an axis was last frame, to tell when it was pushed past a deadzone
+    fn last_gamepad_axis(&self, axis: GamepadAxis) -> f32;
+}
+
+/// Reads everything from the macroquad window
+pub struct WindowBackend {
+    last_frame: Instant,
+}
+
+impl WindowBackend {
+    pub fn new() -> Self {
+        Self {
+            last_frame: Instant::now(),
+        }
+    }
+}
+
+impl Backend for WindowBackend {
+    fn screen_size(&self) -> Vec2 {
+        vec2(screen_width(), screen_height())
+    }
+
+    fn mouse_position(&self) -> Vec2 {
+        // replays feed back the recorded mouse position in place of the live one
+        let (x, y) = replay::mouse_position();
+        vec2(x, y)
+    }
+
+    fn mouse_wheel(&self) -> f32 {
+        let y = replay::mouse_wheel();
+        if y == 0.0 { 0.0 } else { y.signum() }
+    }
+
+    fn typed_chars(&mut self) -> Vec<char> {
+        replay::typed_chars()
+    }
+
+    fn frame_time(&mut self) -> Duration {
+        let now = Instant::now();
+        let delta = now - self.last_frame;
+        self.last_frame = now;
+        delta
+    }
+
+    fn is_key_down(&self, key: KeyCode) -> bool {
+        macroquad::input::is_key_down(key)
+    }
+
+    fn is_key_pressed(&self, key: KeyCode) -> bool {
+        macroquad::input::is_key_pressed(key)
+    }
+
+    fn is_key_released(&self, key: KeyCode) -> bool {
+        macroquad::input::is_key_released(key)
+    }
+
+    fn keys_down(&self) -> Vec<KeyCode> {
+        macroquad::input::get_keys_down().into_iter().collect()
+    }
+
+    fn keys_pressed(&self) -> Vec<KeyCode> {
+        macroquad::input::get_keys_pressed().into_iter().collect()
+    }
+
+    fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        match replay::mouse_button(button) {
+            Some((down, _)) => down,
+            None => macroquad::input::is_mouse_button_down(button),
+        }
+    }
+
+    fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        match replay::mouse_button(button) {
+            Some((down, was_down)) => down && !was_down,
+            None => macroquad::input::is_mouse_button_pressed(button),
+        }
+    }
+
+    fn is_mouse_button_released(&self, button: MouseButton) -> bool {
+        match replay::mouse_button(button) {
+            Some((down, was_down)) => !down && was_down,
+            None => macroquad::input::is_mouse_button_released(button),
+        }
+    }
+
+    fn is_gamepad_connected(&self) -> bool {
+        gamepad::is_connected()
+    }
+
+    fn is_gamepad_button_down(&self, button: GamepadButton) -> bool {
+        gamepad::is_button_down(button)
+    }
+
+    fn is_gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        gamepad::is_button_pressed(button)
+    }
+
+    fn is_gamepad_button_released(&self, button: GamepadButton) -> bool {
+        gamepad::is_button_released(button)
+    }
+
+    fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        gamepad::axis_value(axis)
+    }
+
+    fn last_gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        gamepad::last_axis_value(axis)
+    }
+}
+
+/// A fixed size screen with keys, a mouse and a gamepad that only change when told to, and a clock that advances
+/// exactly `step` every frame. Nothing here touches the window or a real controller.
+/// Keys and buttons changed after `frame_time` count as pressed or released on that frame.
+#[cfg(test)]
+pub struct HeadlessBackend {
+    pub screen_size: Vec2,
+    pub mouse_position: Vec2,
+    pub step: Duration,
+    /// total time simulated so far
+    pub elapsed: Duration,
+    keys: HashSet<KeyCode>,
+    // what was down when the frame started, to tell presses and releases apart
+    last_keys: HashSet<KeyCode>,
+    buttons: HashSet<MouseButton>,
+    last_buttons: HashSet<MouseButton>,
+    pub gamepad_connected: bool,
+    pad_buttons: HashSet<GamepadButton>,
+    last_pad_buttons: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+    last_axes: HashMap<GamepadAxis, f32>,
+}
+
+#[cfg(test)]
+impl HeadlessBackend {
+    pub fn new(screen_size: Vec2, step: Duration) -> Self {
+        Self {
+            screen_size,
+            mouse_position: Vec2::ZERO,
+            step,
+            elapsed: Duration::ZERO,
+            keys: HashSet::new(),
+            last_keys: HashSet::new(),
+            buttons: HashSet::new(),
+            last_buttons: HashSet::new(),
+            gamepad_connected: false,
+            pad_buttons: HashSet::new(),
+            last_pad_buttons: HashSet::new(),
+            axes: HashMap::new(),
+            last_axes: HashMap::new(),
+        }
+    }
+
+    pub fn press_key(&mut self, key: KeyCode) {
+        self.keys.insert(key);
+    }
+
+    pub fn release_key(&mut self, key: KeyCode) {
+        self.keys.remove(&key);
+    }
+
+    pub fn press_mouse_button(&mut self, button: MouseButton) {
+        self.buttons.insert(button);
+    }
+
+    pub fn release_mouse_button(&mut self, button: MouseButton) {
+        self.buttons.remove(&button);
+    }
+
+    /// Scripting any gamepad input connects the gamepad
+    pub fn press_gamepad_button(&mut self, button: GamepadButton) {
+        self.gamepad_connected = true;
+        self.pad_buttons.insert(button);
+    }
+
+    pub fn release_gamepad_button(&mut self, button: GamepadButton) {
+        self.pad_buttons.remove(&button);
+    }
+
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.gamepad_connected = true;
+        self.axes.insert(axis, value);
+    }
+}
+
+#[cfg(test)]
+impl Backend for HeadlessBackend {
+    fn screen_size(&self) -> Vec2 {
+        self.screen_size
+    }
+
+    fn mouse_position(&self) -> Vec2 {
+        self.mouse_position
+    }
+
+    fn mouse_wheel(&self) -> f32 {
+        0.0
+    }
+
+    fn typed_chars(&mut self) -> Vec<char> {
+        Vec::new()
+    }
+
+    fn frame_time(&mut self) -> Duration {
+        self.last_keys = self.keys.clone();
+        self.last_buttons = self.buttons.clone();
+        self.last_pad_buttons = self.pad_buttons.clone();
+        self.last_axes = self.axes.clone();
+        self.elapsed += self.step;
+        self.step
+    }
+
+    fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys.contains(&key)
+    }
+
+    fn is_key_pressed(&self, key: KeyCode) -> bool {
+        self.keys.contains(&key) && !self.last_keys.contains(&key)
+    }
+
+    fn is_key_released(&self, key: KeyCode) -> bool {
+        !self.keys.contains(&key) && self.last_keys.contains(&key)
+    }
+
+    fn keys_down(&self) -> Vec<KeyCode> {
+        self.keys.iter().copied().collect()
+    }
+
+    fn keys_pressed(&self) -> Vec<KeyCode> {
+        self.keys.difference(&self.last_keys).copied().collect()
+    }
+
+    fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button) && !self.last_buttons.contains(&button)
+    }
+
+    fn is_mouse_button_released(&self, button: MouseButton) -> bool {
+        !self.buttons.contains(&button) && self.last_buttons.contains(&button)
+    }
+
+    fn is_gamepad_connected(&self) -> bool {
+        self.gamepad_connected
+    }
+
+    fn is_gamepad_button_down(&self, button: GamepadButton) -> bool {
+        self.pad_buttons.contains(&button)
+    }
+
+    fn is_gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        self.pad_buttons.contains(&button) && !self.last_pad_buttons.contains(&button)
+    }
+
+    fn is_gamepad_button_released(&self, button: GamepadButton) -> bool {
+        !self.pad_buttons.contains(&button) && self.last_pad_buttons.contains(&button)
+    }
+
+    fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    fn last_gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.last_axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
@@ -0,0 +1,107 @@
+use macroquad::math::{vec2, Vec2};
+use macroquad::rand::gen_range;
+use crate::BASE_WINDOW_SIZE;
+
+/// Zoom levels the player can step between, 1.0 shows the same area as the base window size
+pub const ZOOM_LEVELS: [f32; 5] = [0.5, 0.75, 1.0, 1.5, 2.0];
+const DEFAULT_ZOOM_LEVEL: usize = 2;
+
+/// How quickly the camera catches up to its target, higher is snappier
+const FOLLOW_SPEED: f32 = 6.0;
+const ZOOM_SPEED: f32 = 10.0;
+
+/// The furthest the screen moves from a full strength shake, in world units
+const MAX_SHAKE_OFFSET: f32 = 8.0;
+/// How much shake strength is lost per second
+const SHAKE_DECAY: f32 = 1.5;
+
+/// Size in world units of a sprite drawn at zoom 1.0
+pub const BASE_SPRITE_SIZE: f32 = 32.0;
+
+/// Looks at a point in the world. World units are pixels of the base window size,
+/// so at zoom 1.0 a window of `BASE_WINDOW_SIZE` shows exactly that many units across.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    /// the world position at the center of the screen
+    pub position: Vec2,
+    zoom: f32,
+    zoom_level: usize,
+    // 0.0 to 1.0, the offset grows with the square of this so small shakes stay subtle
+    shake: f32,
+    shake_offset: Vec2,
+}
+
+impl Camera {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position,
+            zoom: ZOOM_LEVELS[DEFAULT_ZOOM_LEVEL],
+            zoom_level: DEFAULT_ZOOM_LEVEL,
+            shake: 0.0,
+            shake_offset: Vec2::ZERO,
+        }
+    }
+
+    /// Ease towards `target` and the chosen zoom level, keeping the view inside `bounds` where it fits
+    pub fn update(&mut self, target: Vec2, bounds: Vec2, delta_seconds: f32, screen_size: Vec2) {
+        // frame rate independent smoothing
+        let follow = 1.0 - (-FOLLOW_SPEED * delta_seconds).exp();
+        self.position += (target - self.position) * follow;
+
+        let zoom = 1.0 - (-ZOOM_SPEED * delta_seconds).exp();
+        self.zoom += (ZOOM_LEVELS[self.zoom_level] - self.zoom) * zoom;
+
+        // don't show past the edge of the world, centering it instead if it is smaller than the view
+        let half_view = screen_size / self.scale(screen_size) / 2.0;
+        let clamp_axis = |pos: f32, half: f32, bound: f32| if half * 2.0 >= bound {
+            bound / 2.0
+        } else {
+            pos.clamp(half, bound - half)
+        };
+        self.position = vec2(clamp_axis(self.position.x, half_view.x, bounds.x),
+                             clamp_axis(self.position.y, half_view.y, bounds.y));
+
+        self.shake = (self.shake - SHAKE_DECAY * delta_seconds).max(0.0);
+        self.shake_offset = if self.shake > 0.0 {
+            let strength = self.shake * self.shake * MAX_SHAKE_OFFSET;
+            vec2(gen_range(-1.0, 1.0), gen_range(-1.0, 1.0)) * strength
+        } else {
+            Vec2::ZERO
+        };
+    }
+
+    /// Add screen shake, from 0.0 (nothing) to 1.0 (as strong as it gets)
+    pub fn shake(&mut self, amount: f32) {
+        self.shake = (self.shake + amount).min(1.0);
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.zoom_level = (self.zoom_level + 1).min(ZOOM_LEVELS.len() - 1);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom_level = self.zoom_level.saturating_sub(1);
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Screen pixels per world unit
+    pub fn scale(&self, screen_size: Vec2) -> f32 {
+        screen_size.x / BASE_WINDOW_SIZE.0 as f32 * self.zoom
+    }
+
+    /// The size to draw a standard sprite at
+    pub fn sprite_scale(&self, screen_size: Vec2) -> Vec2 {
+        Vec2::splat(BASE_SPRITE_SIZE * self.scale(screen_size))
+    }
+
+    pub fn world_to_screen(&self, pos: Vec2, screen_size: Vec2) -> Vec2 {
+        (pos - self.position - self.shake_offset) * self.scale(screen_size) + screen_size / 2.0
+    }
+
+    pub fn screen_to_world(&self, pos: Vec2, screen_size: Vec2) -> Vec2 {
+        (pos - screen_size / 2.0) / self.scale(screen_size) + self.position + self.shake_offset
+    }
+}
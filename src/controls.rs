@@ -1,8 +1,20 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::cell::{Cell, RefCell};
 use std::path::Path;
-use macroquad::input::{is_key_down, is_key_released, is_mouse_button_down, is_mouse_button_released, KeyCode, MouseButton};
-use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use macroquad::input::{KeyCode, MouseButton};
+use serde::{Deserialize, Serialize, Serializer};
+use crate::backend::Backend;
+use crate::{gamepad, info, replay, warn};
+use crate::gamepad::{AxisDirection, GamepadAxis, GamepadButton, GamepadInput};
+
+/// Bump this when the layout of the controls file changes.
+/// Version 1 stored keys and mouse buttons as raw numbers, version 2 stores them by name.
+pub const CONTROLS_VERSION: u32 = 2;
+
+const DEFAULT_STICK_DEADZONE: f32 = 0.2;
+const DEFAULT_TRIGGER_DEADZONE: f32 = 0.1;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
 pub enum Action {
@@ -13,6 +25,10 @@ pub enum Action {
     MoveRight,
     Sprint,
 
+    // camera
+    ZoomIn,
+    ZoomOut,
+
     // interaction
     Interact,
     Inventory,
@@ -24,11 +40,148 @@ pub enum Action {
     // UI
     UIClick, // pressing a button
     UIRightClick, // right-click on a button
+    UIUp, // keyboard / gamepad navigation
+    UIDown,
+    UILeft,
+    UIRight,
+    UIConfirm,
+    UIBack,
 
     // MISC
     Debug,
 }
 
+impl Action {
+    /// Every action, in the order they should be listed to the player
+    pub const ALL: [Action; 20] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Sprint,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::Interact,
+        Action::Inventory,
+        Action::Pause,
+        Action::BasicAttack,
+        Action::UIClick,
+        Action::UIRightClick,
+        Action::UIUp,
+        Action::UIDown,
+        Action::UILeft,
+        Action::UIRight,
+        Action::UIConfirm,
+        Action::UIBack,
+        Action::Debug,
+    ];
+
+    /// Actions that are never checked in the same context may share inputs
+    pub fn shares_context(&self, other: &Action) -> bool {
+        InputContext::ALL.iter().any(|c| c.actions().contains(self) && c.actions().contains(other))
+    }
+}
+
+/// Which set of actions is being listened for. Each gamestate and overlay picks one, so the same
+/// input can mean different things (left click attacks while playing but presses buttons in menus).
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum InputContext {
+    #[default]
+    Gameplay,
+    /// in-game panels drawn over the world, like the inventory
+    UI,
+    Minigame,
+    /// the pause menu and everything opened from it
+    Menu,
+}
+
+impl InputContext {
+    pub const ALL: [InputContext; 4] = [
+        InputContext::Gameplay,
+        InputContext::UI,
+        InputContext::Minigame,
+        InputContext::Menu,
+    ];
+
+    /// The actions that can fire in this context, in priority order
+    pub fn actions(&self) -> &'static [Action] {
+        match self {
+            InputContext::Gameplay => &[
+                Action::MoveUp,
+                Action::MoveDown,
+                Action::MoveLeft,
+                Action::MoveRight,
+                Action::Sprint,
+                Action::ZoomIn,
+                Action::ZoomOut,
+                Action::Interact,
+                Action::Inventory,
+                Action::Pause,
+                Action::BasicAttack,
+                Action::Debug,
+            ],
+            InputContext::UI => &[
+                Action::Inventory,
+                Action::Pause,
+                Action::UIClick,
+                Action::UIRightClick,
+                Action::UIUp,
+                Action::UIDown,
+                Action::UILeft,
+                Action::UIRight,
+                Action::UIConfirm,
+                Action::UIBack,
+            ],
+            InputContext::Minigame => &[
+                Action::Pause,
+                Action::BasicAttack,
+                Action::UIClick,
+                Action::UIConfirm,
+                Action::UIBack,
+            ],
+            InputContext::Menu => &[
+                Action::Pause,
+                Action::UIClick,
+                Action::UIRightClick,
+                Action::UIUp,
+                Action::UIDown,
+                Action::UILeft,
+                Action::UIRight,
+                Action::UIConfirm,
+                Action::UIBack,
+            ],
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Action::MoveUp => "Move Up",
+            Action::MoveDown => "Move Down",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Sprint => "Sprint",
+            Action::ZoomIn => "Zoom In",
+            Action::ZoomOut => "Zoom Out",
+            Action::Interact => "Interact",
+            Action::Inventory => "Inventory",
+            Action::Pause => "Pause",
+            Action::BasicAttack => "Attack",
+            Action::UIClick => "UI Click",
+            Action::UIRightClick => "UI Right Click",
+            Action::UIUp => "UI Up",
+            Action::UIDown => "UI Down",
+            Action::UILeft => "UI Left",
+            Action::UIRight => "UI Right",
+            Action::UIConfirm => "UI Confirm",
+            Action::UIBack => "UI Back",
+            Action::Debug => "Debug",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
 pub enum ExpectedPressType {
     /*
@@ -43,25 +196,88 @@ pub enum ExpectedPressType {
     */
     Release,
 
-    // /*
-    // PressCapture - When the button is pressed down, fired once
-    // This will fire one time when the button is pressed down, and will not fire again until after it is released
-    // Can be used for things like the pause menu where the same key triggers open / close
-    // */
-    // PressCapture,
+    /*
+    PressCapture - When the button is pressed down, fired once
+    This will fire one time when the button is pressed down, and will not fire again until after it is released
+    Can be used for things like the pause menu where the same key triggers open / close
+    */
+    PressCapture,
+
+    /*
+    Hold - When the button has been held down for the given number of milliseconds, fired once
+    This will fire one time when the hold duration is reached, and will not fire again until after it is released
+    */
+    Hold(u32),
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Deserialize, Serialize)]
+/// Stored in controls.dat by name ("W", "LShift", "MouseLeft", "PadA") so the file can be edited by hand
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+#[serde(try_from = "StoredBindingType", into = "String")]
 pub enum BindingType {
     Key(u16),
     Mouse(u16),
+    Gamepad(GamepadInput),
 }
 
-impl Into<u16> for BindingType {
-    fn into(self) -> u16 {
-        match self {
-            BindingType::Key(k) => k,
-            BindingType::Mouse(m) => m,
+impl BindingType {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, BindingType::Gamepad(_))
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(button) = string_to_mousecode(name) {
+            return Some(BindingType::Mouse(button as u16));
+        }
+        if let Some(input) = gamepad::string_to_input(name) {
+            return Some(BindingType::Gamepad(input));
+        }
+        string_to_keycode(name).and_then(BindingType::from_key)
+    }
+
+    /// A key binding, or None for keys that couldn't be written to controls.dat and read back
+    pub fn from_key(key: KeyCode) -> Option<Self> {
+        let stored = u16_to_keycode(key as u16);
+        (stored != KeyCode::Unknown && string_to_keycode(&keycode_to_string(stored)) == Some(key))
+            .then_some(BindingType::Key(key as u16))
+    }
+}
+
+impl From<BindingType> for String {
+    fn from(bind: BindingType) -> Self {
+        match bind {
+            BindingType::Key(k) => keycode_to_string(u16_to_keycode(k)),
+            BindingType::Mouse(m) => mousecode_to_string(u16_to_mousecode(m)),
+            BindingType::Gamepad(g) => gamepad::input_to_string(&g),
+        }
+    }
+}
+
+/// Either a binding name or the numeric form written by version 1 controls files
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredBindingType {
+    Name(String),
+    Legacy(LegacyBindingType),
+}
+
+#[derive(Deserialize)]
+enum LegacyBindingType {
+    Key(u16),
+    Mouse(u16),
+    Gamepad(GamepadInput),
+}
+
+impl TryFrom<StoredBindingType> for BindingType {
+    type Error = String;
+
+    fn try_from(stored: StoredBindingType) -> Result<Self, Self::Error> {
+        match stored {
+            StoredBindingType::Name(name) => BindingType::from_name(&name)
+                .ok_or(format!("Unknown key or button `{}`", name)),
+            StoredBindingType::Legacy(LegacyBindingType::Key(k)) => BindingType::from_key(u16_to_keycode(k))
+                .ok_or(format!("Unknown key code {}", k)),
+            StoredBindingType::Legacy(LegacyBindingType::Mouse(m)) => Ok(BindingType::Mouse(m)),
+            StoredBindingType::Legacy(LegacyBindingType::Gamepad(g)) => Ok(BindingType::Gamepad(g)),
         }
     }
 }
@@ -77,22 +293,67 @@ impl Binding {
             binding,
         }
     }
+
+    /// Gamepad bindings are stored separately from keyboard and mouse bindings
+    pub fn is_gamepad(&self) -> bool {
+        self.binding.first().is_some_and(|(bind, _)| bind.is_gamepad())
+    }
 }
 
 impl Display for Binding {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let binding_list = self.binding.iter().map(|(k, _)| { match k {
-            BindingType::Key(k) => keycode_to_string(u16_to_keycode(*k)),
-            BindingType::Mouse(m) => mousecode_to_string(u16_to_mousecode(*m)),
-        } }).collect::<Vec<String>>();
+        let binding_list = self.binding.iter().map(|(k, _)| String::from(*k)).collect::<Vec<String>>();
+        if let Some((_, ExpectedPressType::Hold(ms))) = self.binding.last() {
+            return write!(f, "Hold {} ({}s)", binding_list.join("+"), *ms as f32 / 1000.0);
+        }
         write!(f, "{}", binding_list.join("+"))
     }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ControlHandler {
+    // files from before the version field was added are version 1
+    #[serde(default = "legacy_version")]
+    version: u32,
     toggle_sprint: bool,
+    #[serde(serialize_with = "serialize_bindings")]
     bindings: HashMap<Action, Binding>,
+    // gamepad bindings are kept separately so an action can be bound on both at once
+    #[serde(default = "default_gamepad_bindings", serialize_with = "serialize_bindings")]
+    gamepad_bindings: HashMap<Action, Binding>,
+    #[serde(default = "default_stick_deadzone")]
+    stick_deadzone: f32,
+    #[serde(default = "default_trigger_deadzone")]
+    trigger_deadzone: f32,
+    #[serde(skip)]
+    context: InputContext,
+    // when controls.dat was last written or read by us, to tell when it was edited externally
+    #[serde(skip)]
+    modified: Cell<Option<SystemTime>>,
+    // how long each input has been held down, as (seconds at the previous frame, seconds now)
+    #[serde(skip)]
+    held: RefCell<HashMap<BindingType, (f32, f32)>>,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+/// Write bindings in the order actions are declared, so the file reads the same every time it is saved
+fn serialize_bindings<S: Serializer>(bindings: &HashMap<Action, Binding>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(Action::ALL.iter().filter_map(|action| bindings.get(action).map(|b| (action, b))))
+}
+
+fn default_gamepad_bindings() -> HashMap<Action, Binding> {
+    ControlHandler::default().gamepad_bindings
+}
+
+fn default_stick_deadzone() -> f32 {
+    DEFAULT_STICK_DEADZONE
+}
+
+fn default_trigger_deadzone() -> f32 {
+    DEFAULT_TRIGGER_DEADZONE
 }
 
 impl ControlHandler {
@@ -114,7 +375,7 @@ impl ControlHandler {
         
         // serialize default values
         let defaults = ControlHandler::default();
-        let Ok(serialized) = serde_json::to_string(&defaults) else {
+        let Ok(serialized) = serde_json::to_string_pretty(&defaults) else {
             return Err(format!("Failed to create default control mapping. Delete {} to regenerate.", path.display()));
         };
         
@@ -135,55 +396,251 @@ impl ControlHandler {
             Self::create_default_control_mapping()?;
         }
         
-        let contents = std::fs::read_to_string(path).unwrap();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+
+        let mut file = match serde_json::from_str::<serde_json::Value>(&contents) {
+            Ok(f) => f,
+            Err(e) => return Err(format!("Failed to load control mapping: `{}`. If this error persists, delete {}", e, path.display())),
+        };
 
-        let res = serde_json::from_str(&contents);
+        // bindings are read one at a time so a single bad entry only resets that action
+        let bindings = Self::take_bindings(&mut file, "bindings");
+        let gamepad_bindings = Self::take_bindings(&mut file, "gamepad_bindings");
+        let res = serde_json::from_value::<ControlHandler>(file);
 
         if let Err(e) = res {
             return Err(format!("Failed to load control mapping: `{}`. If this error persists, delete {}", e, path.display()));
         }
 
-        Ok(res.unwrap())
+        // actions added since the file was written, or with bindings that can't be read, get their default bindings
+        let mut handler = res.unwrap();
+        handler.bindings = Self::parse_bindings(bindings);
+        if let Some(gamepad_bindings) = gamepad_bindings {
+            handler.gamepad_bindings = Self::parse_bindings(Some(gamepad_bindings));
+        }
+        let defaults = ControlHandler::default();
+        for (action, binding) in defaults.bindings {
+            handler.bindings.entry(action).or_insert(binding);
+        }
+        for (action, binding) in defaults.gamepad_bindings {
+            handler.gamepad_bindings.entry(action).or_insert(binding);
+        }
+
+        if handler.version < CONTROLS_VERSION {
+            // older files were read fine above, rewriting them is enough to migrate
+            info!("Migrating controls.dat from version {} to {}", handler.version, CONTROLS_VERSION);
+            handler.version = CONTROLS_VERSION;
+            if let Err(e) = handler.save() {
+                // the migrated controls still work, they just get rewritten next time
+                warn!("{}", e);
+            }
+        }
+        handler.mark_synced();
+
+        Ok(handler)
+    }
+
+    /// Take a map of bindings out of the file, leaving an empty map behind for the rest to deserialize with
+    fn take_bindings(file: &mut serde_json::Value, field: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+        let entry = file.as_object_mut()?.get_mut(field)?;
+        match std::mem::replace(entry, serde_json::Value::Object(serde_json::Map::new())) {
+            serde_json::Value::Object(map) => Some(map),
+            other => {
+                warn!("Ignoring `{}` in controls.dat, it should be a map of actions to bindings but is `{}`", field, other);
+                Some(serde_json::Map::new())
+            }
+        }
+    }
+
+    fn parse_bindings(map: Option<serde_json::Map<String, serde_json::Value>>) -> HashMap<Action, Binding> {
+        let mut bindings = HashMap::new();
+        for (name, value) in map.unwrap_or_default() {
+            let action = match serde_json::from_value::<Action>(serde_json::Value::String(name.clone())) {
+                Ok(a) => a,
+                Err(_) => {
+                    warn!("Ignoring binding for unknown action `{}` in controls.dat", name);
+                    continue;
+                }
+            };
+            match serde_json::from_value::<Binding>(value) {
+                Ok(binding) => {
+                    bindings.insert(action, binding);
+                }
+                Err(e) => warn!("Using the default binding for {}, the one in controls.dat can't be read: {}", action, e),
+            }
+        }
+        bindings
+    }
+
+    fn modified_on_disk() -> Option<SystemTime> {
+        std::fs::metadata("./data/controls.dat").and_then(|m| m.modified()).ok()
+    }
+
+    /// Whether controls.dat has been changed by something other than this handler since it was loaded or saved
+    pub fn changed_on_disk(&self) -> bool {
+        let on_disk = Self::modified_on_disk();
+        on_disk.is_some() && on_disk != self.modified.get()
+    }
+
+    /// Treat the file on disk as up to date with this handler, so `changed_on_disk` only reports newer edits
+    pub fn mark_synced(&self) {
+        self.modified.set(Self::modified_on_disk());
     }
 
     /// Save the control mapping to the file
-    pub fn save(&self) {
+    pub fn save(&self) -> Result<(), String> {
         let raw_path = "./data/controls.dat".to_string();
         let path = Path::new(&raw_path);
         
         if !path.exists() {
-            Self::create_default_control_mapping().unwrap();
+            Self::create_default_control_mapping()?;
         }
         
-        let serialized = serde_json::to_string(self).unwrap();
+        let serialized = match serde_json::to_string_pretty(self) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("Failed to serialize control mapping: {}", e)),
+        };
         
-        std::fs::write(path, serialized).unwrap();
+        if let Err(e) = std::fs::write(path, serialized) {
+            return Err(format!("Failed to save {}: {}", path.display(), e));
+        }
+        self.mark_synced();
+        Ok(())
     }
 
-    /// Get the actions that have occurred
-    pub fn get_actions(&self) -> Vec<Action> {
+    /// Get the actions that have occurred in the active context.
+    /// Each input only triggers one action per frame, with longer chords taking priority (Shift+W over W).
+    pub fn get_actions(&self, backend: &dyn Backend) -> Vec<Action> {
+        if let Some(actions) = replay::recorded_actions(self.context) {
+            return actions;
+        }
+
+        let mut candidates = self.context.actions().to_vec();
+        candidates.sort_by_key(|a| std::cmp::Reverse(self.chord_length(a)));
+
         let mut active = Vec::new();
+        let mut consumed: Vec<BindingType> = Vec::new();
 
-        for action in self.bindings.keys() {
-            if self.is_action_active(action) {
-                active.push(action.clone());
+        for action in candidates {
+            let bindings = [self.bindings.get(&action), self.gamepad_bindings.get(&action)];
+            let Some(binding) = bindings.into_iter().flatten().find(|b| self.is_binding_active(b, backend)) else {
+                continue;
+            };
+            if binding.binding.iter().any(|(bind, _)| consumed.contains(bind)) {
+                continue;
             }
+            consumed.extend(binding.binding.iter().map(|(bind, _)| *bind));
+            active.push(action);
         }
 
+        replay::record_actions(self.context, &active);
         active
     }
 
-    pub fn is_action_active(&self, action: &Action) -> bool {
+    fn chord_length(&self, action: &Action) -> usize {
+        [self.bindings.get(action), self.gamepad_bindings.get(action)].into_iter()
+            .flatten()
+            .map(|b| b.binding.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Switch which actions `get_actions` listens for
+    pub fn set_context(&mut self, context: InputContext) {
+        self.context = context;
+    }
+
+    pub fn get_context(&self) -> InputContext {
+        self.context
+    }
+
+    /// An action is active if either its keyboard / mouse or gamepad binding is
+    pub fn is_action_active(&self, action: &Action, backend: &dyn Backend) -> bool {
+        self.bindings.get(action).is_some_and(|b| self.is_binding_active(b, backend))
+            || self.is_gamepad_action_active(action, backend)
+    }
+
+    /// Whether the action is active from its gamepad binding, for things that behave differently without a mouse
+    pub fn is_gamepad_action_active(&self, action: &Action, backend: &dyn Backend) -> bool {
+        if let Some(active) = replay::recorded_gamepad_action(action) {
+            return active;
+        }
+        let active = self.gamepad_bindings.get(action).is_some_and(|b| self.is_binding_active(b, backend));
+        replay::record_gamepad_action(action, active);
+        active
+    }
+
+    /// How strongly an action is pressed, from 0.0 to 1.0.
+    /// Digital inputs are always 1.0 when active, analog sticks and triggers scale with how far they are pushed.
+    pub fn action_strength(&self, action: &Action, backend: &dyn Backend) -> f32 {
+        if let Some(strength) = replay::recorded_strength(action) {
+            return strength;
+        }
+        let strength = self.live_action_strength(action, backend);
+        replay::record_strength(action, strength);
+        strength
+    }
+
+    fn live_action_strength(&self, action: &Action, backend: &dyn Backend) -> f32 {
+        if self.bindings.get(action).is_some_and(|b| self.is_binding_active(b, backend)) {
+            return 1.0;
+        }
+        let Some(binding) = self.gamepad_bindings.get(action) else {
+            return 0.0;
+        };
+        if !self.is_binding_active(binding, backend) {
+            return 0.0;
+        }
+        binding.binding.iter()
+            .map(|(bind, _)| match bind {
+                BindingType::Gamepad(input) => gamepad::input_strength(backend, input, self.deadzone_for(input)),
+                _ => 1.0,
+            })
+            .fold(1.0, f32::min)
+    }
+
+    pub fn deadzone_for(&self, input: &GamepadInput) -> f32 {
+        match input {
+            GamepadInput::Axis(axis, _) if axis.is_trigger() => self.trigger_deadzone,
+            _ => self.stick_deadzone,
+        }
+    }
+
+    pub fn stick_deadzone(&self) -> f32 {
+        self.stick_deadzone
+    }
+
+    pub fn trigger_deadzone(&self) -> f32 {
+        self.trigger_deadzone
+    }
+
+    fn is_binding_active(&self, binding: &Binding, backend: &dyn Backend) -> bool {
+        if binding.binding.is_empty() {
+            return false;
+        }
         let mut is_active = true;
-        for (bind, ept) in &self.bindings.get(&action).unwrap().binding {
+        for (bind, ept) in &binding.binding {
             match ept {
                 ExpectedPressType::Press => {
-                    if !self.is_bind_pressed(bind) {
+                    if !self.is_bind_pressed(bind, backend) {
                         is_active = false;
                     }
                 }
                 ExpectedPressType::Release => {
-                    if !self.is_bind_released(bind) {
+                    if !self.is_bind_released(bind, backend) {
+                        is_active = false;
+                    }
+                }
+                ExpectedPressType::PressCapture => {
+                    if !self.is_bind_just_pressed(bind, backend) {
+                        is_active = false;
+                    }
+                }
+                ExpectedPressType::Hold(ms) => {
+                    if !self.is_hold_reached(bind, *ms as f32 / 1000.0) {
                         is_active = false;
                     }
                 }
@@ -193,24 +650,48 @@ impl ControlHandler {
         is_active
     }
 
-    fn is_bind_pressed(&self, bind: &BindingType) -> bool {
+    /// True only on the frame an input's hold time crosses `seconds`
+    fn is_hold_reached(&self, bind: &BindingType, seconds: f32) -> bool {
+        self.held.borrow()
+            .get(bind)
+            .is_some_and(|(previous, now)| *previous < seconds && *now >= seconds)
+    }
+
+    fn is_bind_just_pressed(&self, bind: &BindingType, backend: &dyn Backend) -> bool {
         match bind {
-            BindingType::Key(key) => is_key_down(u16_to_keycode(*key)),
-            BindingType::Mouse(mb) => is_mouse_button_down(u16_to_mousecode(*mb)),
+            BindingType::Key(key) => backend.is_key_pressed(u16_to_keycode(*key)),
+            BindingType::Mouse(mb) => backend.is_mouse_button_pressed(u16_to_mousecode(*mb)),
+            BindingType::Gamepad(g) => gamepad::is_input_pressed(backend, g, self.deadzone_for(g)),
         }
     }
 
-    fn is_bind_released(&self, bind: &BindingType) -> bool {
+    fn is_bind_pressed(&self, bind: &BindingType, backend: &dyn Backend) -> bool {
         match bind {
-            BindingType::Key(key) => is_key_released(u16_to_keycode(*key)),
-            BindingType::Mouse(mb) => is_mouse_button_released(u16_to_mousecode(*mb)),
+            BindingType::Key(key) => backend.is_key_down(u16_to_keycode(*key)),
+            BindingType::Mouse(mb) => backend.is_mouse_button_down(u16_to_mousecode(*mb)),
+            BindingType::Gamepad(g) => gamepad::is_input_down(backend, g, self.deadzone_for(g)),
+        }
+    }
+
+    fn is_bind_released(&self, bind: &BindingType, backend: &dyn Backend) -> bool {
+        match bind {
+            BindingType::Key(key) => backend.is_key_released(u16_to_keycode(*key)),
+            BindingType::Mouse(mb) => backend.is_mouse_button_released(u16_to_mousecode(*mb)),
+            BindingType::Gamepad(g) => gamepad::is_input_released(backend, g, self.deadzone_for(g)),
         }
     }
     
-    pub fn edit_keybind(&mut self, action: Action, new_key: Binding) {
+    /// Rebind an action. The new binding is used even if saving it fails.
+    pub fn edit_keybind(&mut self, action: Action, new_key: Binding) -> Result<(), String> {
         self.bindings.insert(action, new_key);
         
-        self.save();
+        self.save()
+    }
+
+    pub fn edit_gamepad_binding(&mut self, action: Action, new_binding: Binding) -> Result<(), String> {
+        self.gamepad_bindings.insert(action, new_binding);
+
+        self.save()
     }
 
     pub fn get_binding(&self, action: &Action) -> Option<Binding> {
@@ -220,23 +701,108 @@ impl ControlHandler {
         None
     }
 
+    pub fn get_gamepad_binding(&self, action: &Action) -> Option<Binding> {
+        self.gamepad_bindings.get(action).cloned()
+    }
+
+    /// How long the action's binding has to be held before it fires, if it's a hold binding.
+    /// The keyboard binding is checked first, both are kept the same by `set_hold_time`.
+    pub fn hold_time(&self, action: &Action) -> Option<u32> {
+        [&self.bindings, &self.gamepad_bindings].into_iter()
+            .filter_map(|bindings| bindings.get(action).and_then(|b| b.binding.last()))
+            .find_map(|(_, ept)| match ept {
+                ExpectedPressType::Hold(ms) => Some(*ms),
+                _ => None,
+            })
+    }
+
+    /// Make both of the action's bindings fire after being held for `ms`,
+    /// or with `None` go back to firing the way the default binding does
+    pub fn set_hold_time(&mut self, action: &Action, ms: Option<u32>) -> Result<(), String> {
+        let defaults = ControlHandler::default();
+        let targets = [
+            (&mut self.bindings, defaults.get_binding(action)),
+            (&mut self.gamepad_bindings, defaults.get_gamepad_binding(action)),
+        ];
+        for (bindings, default) in targets {
+            let tap = default
+                .and_then(|b| b.binding.last().map(|(_, ept)| ept.clone()))
+                .unwrap_or(ExpectedPressType::Press);
+            if let Some((_, last)) = bindings.get_mut(action).and_then(|b| b.binding.last_mut()) {
+                *last = ms.map_or(tap, ExpectedPressType::Hold);
+            }
+        }
+        if *action == Action::Sprint && ms.is_none() {
+            // keep the player's toggle preference
+            return self.set_sprint_toggle(self.toggle_sprint);
+        }
+        self.save()
+    }
+
     pub fn is_sprint_toggle(&self) -> bool {
         self.toggle_sprint
     }
 
-    pub fn set_sprint_toggle(&mut self, toggle: bool) {
-        let current_sprint_bind = self.bindings.get(&Action::Sprint).unwrap();
-        if toggle {
+    pub fn set_sprint_toggle(&mut self, toggle: bool) -> Result<(), String> {
+        // modifiers in a chord are always held, only the last key decides between toggle and hold
+        let ept = if toggle {
             // Change the sprint binding to toggle, using the key that was already bound
-            let new_sprint_bind = Binding::new(vec![(current_sprint_bind.binding[0].0, ExpectedPressType::Release)]);
-            self.edit_keybind(Action::Sprint, new_sprint_bind);
+            ExpectedPressType::PressCapture
         } else {
             // Change the sprint binding to hold, using the key that was already bound
-            let new_sprint_bind = Binding::new(vec![(current_sprint_bind.binding[0].0, ExpectedPressType::Press)]);
-            self.edit_keybind(Action::Sprint, new_sprint_bind);
+            ExpectedPressType::Press
+        };
+        for bindings in [&mut self.bindings, &mut self.gamepad_bindings] {
+            if let Some((_, last)) = bindings.get_mut(&Action::Sprint).and_then(|b| b.binding.last_mut()) {
+                *last = ept.clone();
+            }
         }
         // reflect the change in the struct
         self.toggle_sprint = toggle;
+        self.save()
+    }
+
+    /// Restore the default binding for a single action
+    pub fn reset_binding(&mut self, action: &Action) -> Result<(), String> {
+        let defaults = ControlHandler::default();
+        if let Some(default) = defaults.get_binding(action) {
+            self.bindings.insert(action.clone(), default);
+        }
+        if let Some(default) = defaults.get_gamepad_binding(action) {
+            self.gamepad_bindings.insert(action.clone(), default);
+        }
+        if *action == Action::Sprint {
+            // keep the player's toggle preference
+            return self.set_sprint_toggle(self.toggle_sprint);
+        }
+        self.save()
+    }
+
+    /// Restore every binding to its default, keeping the sprint toggle and deadzones like `reset_binding` does
+    pub fn reset_all(&mut self) -> Result<(), String> {
+        let defaults = ControlHandler::default();
+        self.bindings = defaults.bindings;
+        self.gamepad_bindings = defaults.gamepad_bindings;
+        // saves too
+        self.set_sprint_toggle(self.toggle_sprint)
+    }
+
+    /// Get the other actions that would fire from the same inputs as `binding`.
+    /// Actions that are never active in the same input context don't conflict with each other.
+    pub fn find_conflicts(&self, action: &Action, binding: &Binding) -> Vec<Action> {
+        let inputs = |b: &Binding| {
+            let mut inputs = b.binding.iter().map(|(bind, _)| *bind).collect::<Vec<BindingType>>();
+            inputs.sort();
+            inputs
+        };
+        let wanted = inputs(binding);
+        let bindings = if binding.is_gamepad() { &self.gamepad_bindings } else { &self.bindings };
+
+        Action::ALL.iter()
+            .filter(|other| *other != action && other.shares_context(action))
+            .filter(|other| bindings.get(other).is_some_and(|b| inputs(b) == wanted))
+            .cloned()
+            .collect()
     }
 }
 
@@ -262,16 +828,23 @@ impl Default for ControlHandler {
         bindings.insert(Action::Sprint, Binding::new(vec!((BindingType::Key(KeyCode::LeftShift as u16),
                                                            ExpectedPressType::Press))));
 
+        // == Camera ==
+
+        bindings.insert(Action::ZoomIn, Binding::new(vec!((BindingType::Key(KeyCode::Equal as u16),
+                                                           ExpectedPressType::PressCapture))));
+        bindings.insert(Action::ZoomOut, Binding::new(vec!((BindingType::Key(KeyCode::Minus as u16),
+                                                            ExpectedPressType::PressCapture))));
+
         // == Interaction ==
 
         bindings.insert(Action::Interact, Binding::new(vec!((BindingType::Mouse(MouseButton::Right as u16),
-                                                             ExpectedPressType::Release))));
+                                                             ExpectedPressType::PressCapture))));
 
         bindings.insert(Action::Inventory, Binding::new(vec!((BindingType::Key(KeyCode::Tab as u16),
-                                                             ExpectedPressType::Release))));
+                                                             ExpectedPressType::PressCapture))));
 
         bindings.insert(Action::Pause, Binding::new(vec!((BindingType::Key(KeyCode::Escape as u16),
-                                                             ExpectedPressType::Release))));
+                                                             ExpectedPressType::PressCapture))));
 
         // == UI ==
 
@@ -280,6 +853,19 @@ impl Default for ControlHandler {
         bindings.insert(Action::UIRightClick, Binding::new(vec!((BindingType::Mouse(MouseButton::Right as u16),
                                                             ExpectedPressType::Release))));
 
+        bindings.insert(Action::UIUp, Binding::new(vec!((BindingType::Key(KeyCode::Up as u16),
+                                                         ExpectedPressType::PressCapture))));
+        bindings.insert(Action::UIDown, Binding::new(vec!((BindingType::Key(KeyCode::Down as u16),
+                                                           ExpectedPressType::PressCapture))));
+        bindings.insert(Action::UILeft, Binding::new(vec!((BindingType::Key(KeyCode::Left as u16),
+                                                           ExpectedPressType::PressCapture))));
+        bindings.insert(Action::UIRight, Binding::new(vec!((BindingType::Key(KeyCode::Right as u16),
+                                                            ExpectedPressType::PressCapture))));
+        bindings.insert(Action::UIConfirm, Binding::new(vec!((BindingType::Key(KeyCode::Enter as u16),
+                                                              ExpectedPressType::PressCapture))));
+        bindings.insert(Action::UIBack, Binding::new(vec!((BindingType::Key(KeyCode::Backspace as u16),
+                                                           ExpectedPressType::PressCapture))));
+
         // == Combat ==
 
         bindings.insert(Action::BasicAttack, Binding::new(vec!((BindingType::Mouse(MouseButton::Left as u16),
@@ -288,15 +874,82 @@ impl Default for ControlHandler {
         // == Misc ==
 
         bindings.insert(Action::Debug, Binding::new(vec!((BindingType::Key(KeyCode::GraveAccent as u16),
-                                                           ExpectedPressType::Release))));
+                                                           ExpectedPressType::PressCapture))));
         
+        // == Gamepad ==
+
+        let mut gamepad_bindings = HashMap::new();
+        let pad = |input: GamepadInput, ept: ExpectedPressType| Binding::new(vec!((BindingType::Gamepad(input), ept)));
+
+        gamepad_bindings.insert(Action::MoveUp, pad(GamepadInput::Axis(GamepadAxis::LeftStickY, AxisDirection::Negative), ExpectedPressType::Press));
+        gamepad_bindings.insert(Action::MoveDown, pad(GamepadInput::Axis(GamepadAxis::LeftStickY, AxisDirection::Positive), ExpectedPressType::Press));
+        gamepad_bindings.insert(Action::MoveLeft, pad(GamepadInput::Axis(GamepadAxis::LeftStickX, AxisDirection::Negative), ExpectedPressType::Press));
+        gamepad_bindings.insert(Action::MoveRight, pad(GamepadInput::Axis(GamepadAxis::LeftStickX, AxisDirection::Positive), ExpectedPressType::Press));
+        gamepad_bindings.insert(Action::Sprint, pad(GamepadInput::Button(GamepadButton::LeftStick), ExpectedPressType::Press));
+
+        gamepad_bindings.insert(Action::ZoomIn, pad(GamepadInput::Button(GamepadButton::RightBumper), ExpectedPressType::PressCapture));
+        gamepad_bindings.insert(Action::ZoomOut, pad(GamepadInput::Button(GamepadButton::LeftBumper), ExpectedPressType::PressCapture));
+
+        gamepad_bindings.insert(Action::Interact, pad(GamepadInput::Button(GamepadButton::West), ExpectedPressType::PressCapture));
+        gamepad_bindings.insert(Action::Inventory, pad(GamepadInput::Button(GamepadButton::North), ExpectedPressType::PressCapture));
+        gamepad_bindings.insert(Action::Pause, pad(GamepadInput::Button(GamepadButton::Start), ExpectedPressType::PressCapture));
+
+        gamepad_bindings.insert(Action::BasicAttack, pad(GamepadInput::Axis(GamepadAxis::RightTrigger, AxisDirection::Positive), ExpectedPressType::Press));
+
+        gamepad_bindings.insert(Action::UIUp, pad(GamepadInput::Button(GamepadButton::DPadUp), ExpectedPressType::PressCapture));
+        gamepad_bindings.insert(Action::UIDown, pad(GamepadInput::Button(GamepadButton::DPadDown), ExpectedPressType::PressCapture));
+        gamepad_bindings.insert(Action::UILeft, pad(GamepadInput::Button(GamepadButton::DPadLeft), ExpectedPressType::PressCapture));
+        gamepad_bindings.insert(Action::UIRight, pad(GamepadInput::Button(GamepadButton::DPadRight), ExpectedPressType::PressCapture));
+        gamepad_bindings.insert(Action::UIConfirm, pad(GamepadInput::Button(GamepadButton::South), ExpectedPressType::PressCapture));
+        gamepad_bindings.insert(Action::UIBack, pad(GamepadInput::Button(GamepadButton::East), ExpectedPressType::PressCapture));
+
+        gamepad_bindings.insert(Action::Debug, pad(GamepadInput::Button(GamepadButton::Select), ExpectedPressType::PressCapture));
+
         Self {
+            version: CONTROLS_VERSION,
             bindings,
+            gamepad_bindings,
             toggle_sprint: false,
+            stick_deadzone: DEFAULT_STICK_DEADZONE,
+            trigger_deadzone: DEFAULT_TRIGGER_DEADZONE,
+            context: InputContext::default(),
+            modified: Cell::new(None),
+            held: RefCell::new(HashMap::new()),
         }
     }
 }
 
+/// Advance the hold timers for every input that is currently down. Must be called once per frame.
+/// Axes count as down past the handler's deadzones, the same as when checking if they're pressed.
+pub fn update_held_inputs(delta_seconds: f32, handler: &ControlHandler, backend: &dyn Backend) {
+    let mut down = backend.keys_down().into_iter()
+        .map(|k| BindingType::Key(k as u16))
+        .collect::<Vec<BindingType>>();
+    down.extend([MouseButton::Left, MouseButton::Middle, MouseButton::Right].into_iter()
+        .filter(|mb| backend.is_mouse_button_down(*mb))
+        .map(|mb| BindingType::Mouse(mb as u16)));
+    down.extend(GamepadButton::ALL.into_iter()
+        .filter(|b| backend.is_gamepad_button_down(*b))
+        .map(|b| BindingType::Gamepad(GamepadInput::Button(b))));
+    for axis in GamepadAxis::ALL {
+        for direction in [AxisDirection::Positive, AxisDirection::Negative] {
+            let input = GamepadInput::Axis(axis, direction);
+            if gamepad::is_input_down(backend, &input, handler.deadzone_for(&input)) {
+                down.push(BindingType::Gamepad(input));
+            }
+        }
+    }
+
+    let mut held = handler.held.borrow_mut();
+    held.retain(|bind, _| down.contains(bind));
+    for bind in down {
+        let (previous, now) = held.entry(bind).or_insert((0.0, 0.0));
+        *previous = *now;
+        *now += delta_seconds;
+    }
+}
+
+
 pub fn u16_to_keycode(key: u16) -> KeyCode {
     match key {
         0x0020 => KeyCode::Space,
@@ -482,8 +1135,8 @@ pub fn keycode_to_string(key: KeyCode) -> String {
         KeyCode::Backslash => "\\",
         KeyCode::RightBracket => "]",
         KeyCode::GraveAccent => "`",
-        KeyCode::World1 => "UKNWN",
-        KeyCode::World2 => "UKNWN",
+        KeyCode::World1 => "World1",
+        KeyCode::World2 => "World2",
         KeyCode::Escape => "Esc",
         KeyCode::Enter => "Enter",
         KeyCode::Tab => "Tab",
@@ -559,6 +1212,134 @@ pub fn keycode_to_string(key: KeyCode) -> String {
     }.to_string()
 }
 
+/// The inverse of `keycode_to_string`, used to read key names from controls.dat
+pub fn string_to_keycode(name: &str) -> Option<KeyCode> {
+    Some(match name {
+        "Space" => KeyCode::Space,
+        "'" => KeyCode::Apostrophe,
+        "," => KeyCode::Comma,
+        "-" => KeyCode::Minus,
+        "." => KeyCode::Period,
+        "/" => KeyCode::Slash,
+        "0" => KeyCode::Key0,
+        "1" => KeyCode::Key1,
+        "2" => KeyCode::Key2,
+        "3" => KeyCode::Key3,
+        "4" => KeyCode::Key4,
+        "5" => KeyCode::Key5,
+        "6" => KeyCode::Key6,
+        "7" => KeyCode::Key7,
+        "8" => KeyCode::Key8,
+        "9" => KeyCode::Key9,
+        ";" => KeyCode::Semicolon,
+        "=" => KeyCode::Equal,
+        "A" => KeyCode::A,
+        "B" => KeyCode::B,
+        "C" => KeyCode::C,
+        "D" => KeyCode::D,
+        "E" => KeyCode::E,
+        "F" => KeyCode::F,
+        "G" => KeyCode::G,
+        "H" => KeyCode::H,
+        "I" => KeyCode::I,
+        "J" => KeyCode::J,
+        "K" => KeyCode::K,
+        "L" => KeyCode::L,
+        "M" => KeyCode::M,
+        "N" => KeyCode::N,
+        "O" => KeyCode::O,
+        "P" => KeyCode::P,
+        "Q" => KeyCode::Q,
+        "R" => KeyCode::R,
+        "S" => KeyCode::S,
+        "T" => KeyCode::T,
+        "U" => KeyCode::U,
+        "V" => KeyCode::V,
+        "W" => KeyCode::W,
+        "X" => KeyCode::X,
+        "Y" => KeyCode::Y,
+        "Z" => KeyCode::Z,
+        "[" => KeyCode::LeftBracket,
+        "\\" => KeyCode::Backslash,
+        "]" => KeyCode::RightBracket,
+        "`" => KeyCode::GraveAccent,
+        "World1" => KeyCode::World1,
+        "World2" => KeyCode::World2,
+        "Esc" => KeyCode::Escape,
+        "Enter" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "BkSpace" => KeyCode::Backspace,
+        "Ins" => KeyCode::Insert,
+        "Del" => KeyCode::Delete,
+        "Right" => KeyCode::Right,
+        "Left" => KeyCode::Left,
+        "Down" => KeyCode::Down,
+        "Up" => KeyCode::Up,
+        "PgUp" => KeyCode::PageUp,
+        "PgDn" => KeyCode::PageDown,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "Caps" => KeyCode::CapsLock,
+        "Scroll" => KeyCode::ScrollLock,
+        "Num" => KeyCode::NumLock,
+        "PrntScrn" => KeyCode::PrintScreen,
+        "Pause" => KeyCode::Pause,
+        "F1" => KeyCode::F1,
+        "F2" => KeyCode::F2,
+        "F3" => KeyCode::F3,
+        "F4" => KeyCode::F4,
+        "F5" => KeyCode::F5,
+        "F6" => KeyCode::F6,
+        "F7" => KeyCode::F7,
+        "F8" => KeyCode::F8,
+        "F9" => KeyCode::F9,
+        "F10" => KeyCode::F10,
+        "F11" => KeyCode::F11,
+        "F12" => KeyCode::F12,
+        "F13" => KeyCode::F13,
+        "F14" => KeyCode::F14,
+        "F15" => KeyCode::F15,
+        "F16" => KeyCode::F16,
+        "F17" => KeyCode::F17,
+        "F18" => KeyCode::F18,
+        "F19" => KeyCode::F19,
+        "F20" => KeyCode::F20,
+        "F21" => KeyCode::F21,
+        "F22" => KeyCode::F22,
+        "F23" => KeyCode::F23,
+        "F24" => KeyCode::F24,
+        "F25" => KeyCode::F25,
+        "Num0" => KeyCode::Kp0,
+        "Num1" => KeyCode::Kp1,
+        "Num2" => KeyCode::Kp2,
+        "Num3" => KeyCode::Kp3,
+        "Num4" => KeyCode::Kp4,
+        "Num5" => KeyCode::Kp5,
+        "Num6" => KeyCode::Kp6,
+        "Num7" => KeyCode::Kp7,
+        "Num8" => KeyCode::Kp8,
+        "Num9" => KeyCode::Kp9,
+        "Num." => KeyCode::KpDecimal,
+        "Num/" => KeyCode::KpDivide,
+        "Num*" => KeyCode::KpMultiply,
+        "Num-" => KeyCode::KpSubtract,
+        "Num+" => KeyCode::KpAdd,
+        "NumEnter" => KeyCode::KpEnter,
+        "Num=" => KeyCode::KpEqual,
+        "LShift" => KeyCode::LeftShift,
+        "LCtrl" => KeyCode::LeftControl,
+        "LAlt" => KeyCode::LeftAlt,
+        "LSuper" => KeyCode::LeftSuper,
+        "RShift" => KeyCode::RightShift,
+        "RControl" => KeyCode::RightControl,
+        "RAlt" => KeyCode::RightAlt,
+        "RSuper" => KeyCode::RightSuper,
+        "Menu" => KeyCode::Menu,
+        "Back" => KeyCode::Back,
+        _ => return None,
+    })
+}
+
 pub fn mousecode_to_string(key: MouseButton) -> String {
     match key {
         MouseButton::Left => "MouseLeft",
@@ -566,4 +1347,99 @@ pub fn mousecode_to_string(key: MouseButton) -> String {
         MouseButton::Right => "MouseRight",
         MouseButton::Unknown => "MouseUNKWN",
     }.to_string()
-}
\ No newline at end of file
+}
+
+/// The inverse of `mousecode_to_string`
+pub fn string_to_mousecode(name: &str) -> Option<MouseButton> {
+    match name {
+        "MouseLeft" => Some(MouseButton::Left),
+        "MouseMiddle" => Some(MouseButton::Middle),
+        "MouseRight" => Some(MouseButton::Right),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use macroquad::math::vec2;
+    use super::*;
+    use crate::backend::HeadlessBackend;
+
+    fn menu_controls() -> ControlHandler {
+        let mut controls = ControlHandler::default();
+        controls.set_context(InputContext::Menu);
+        controls
+    }
+
+    #[test]
+    fn press_capture_fires_once_per_press() {
+        let controls = menu_controls();
+        let mut backend = HeadlessBackend::new(vec2(800.0, 600.0), Duration::from_millis(16));
+
+        backend.frame_time();
+        backend.press_key(KeyCode::Enter);
+        assert!(controls.get_actions(&backend).contains(&Action::UIConfirm));
+
+        backend.frame_time();
+        assert!(!controls.get_actions(&backend).contains(&Action::UIConfirm));
+
+        backend.frame_time();
+        backend.release_key(KeyCode::Enter);
+        backend.frame_time();
+        backend.press_key(KeyCode::Enter);
+        assert!(controls.get_actions(&backend).contains(&Action::UIConfirm));
+    }
+
+    #[test]
+    fn clicks_fire_on_release() {
+        let controls = menu_controls();
+        let mut backend = HeadlessBackend::new(vec2(800.0, 600.0), Duration::from_millis(16));
+
+        backend.frame_time();
+        backend.press_mouse_button(MouseButton::Left);
+        assert!(!controls.get_actions(&backend).contains(&Action::UIClick));
+
+        backend.frame_time();
+        backend.release_mouse_button(MouseButton::Left);
+        assert!(controls.get_actions(&backend).contains(&Action::UIClick));
+    }
+
+    #[test]
+    fn scripted_gamepad_buttons_fire_actions() {
+        let controls = menu_controls();
+        let mut backend = HeadlessBackend::new(vec2(800.0, 600.0), Duration::from_millis(16));
+        assert!(!backend.is_gamepad_connected());
+
+        backend.frame_time();
+        backend.press_gamepad_button(GamepadButton::South);
+        assert!(controls.get_actions(&backend).contains(&Action::UIConfirm));
+
+        backend.frame_time();
+        backend.release_gamepad_button(GamepadButton::South);
+        assert!(!controls.get_actions(&backend).contains(&Action::UIConfirm));
+    }
+
+    #[test]
+    fn hold_fires_once_after_the_hold_time() {
+        let mut controls = menu_controls();
+        controls.bindings.insert(Action::UIConfirm, Binding::new(vec![(BindingType::Key(KeyCode::Enter as u16), ExpectedPressType::Hold(500))]));
+        let mut backend = HeadlessBackend::new(vec2(800.0, 600.0), Duration::from_millis(16));
+        let frame = |backend: &mut HeadlessBackend| {
+            let step = backend.frame_time().as_secs_f32();
+            update_held_inputs(step, &controls, backend);
+            controls.get_actions(backend).contains(&Action::UIConfirm)
+        };
+
+        backend.press_key(KeyCode::Enter);
+        // 16ms frames cross half a second on the 32nd frame held
+        let fired = (1..=60).filter(|_| frame(&mut backend)).count();
+        assert_eq!(fired, 1);
+
+        backend.release_key(KeyCode::Enter);
+        assert!(!frame(&mut backend));
+        backend.press_key(KeyCode::Enter);
+        let fired_at = (1..=60).find(|_| frame(&mut backend));
+        assert_eq!(fired_at, Some(32));
+    }
+}
@@ -1,27 +1,200 @@
 use crate::assets::GlobalAssets;
+use crate::backend::Backend;
+use crate::camera::Camera;
 use crate::controls::ControlHandler;
+use rand::rngs::StdRng;
+use crate::item::ItemRegistry;
+use crate::loot::LootTables;
+use crate::{info, warn};
 use crate::settings::Settings;
 use crate::world::World;
+use crate::world::inventory::ItemStack;
+use crate::world::scene::{self, get_scene, load_scene, SceneManager};
+use crate::save::{SaveGame, SaveSlot};
+use crate::ui::popup::{Notifications, PopupCard, PopupLocation};
+
+/// How often to check controls.dat for edits made outside the game, in seconds
+const CONTROLS_CHECK_INTERVAL: f32 = 1.0;
 
 pub struct GameData {
     // Render Data
     pub fps: f32,
+    // how far the frame is between the last fixed update and the next one, from 0.0 to 1.0
+    pub interpolation: f32,
     // Global Data
     pub settings: Settings,
     pub assets: GlobalAssets,
+    // every item, loaded from assets/data/items.json
+    pub items: ItemRegistry,
+    // what each interactable drops, loaded from assets/data/loot.json
+    pub loot: LootTables,
+    // seeded with --seed or the replay so drops can be repeated
+    pub loot_rng: StdRng,
     pub control_handler: ControlHandler,
     pub world: World,
+    // which scene `world` is, and the worlds of scenes the player has left
+    pub scenes: SceneManager,
+    pub camera: Camera,
+    // toasts like "Rock broken!", drawn over everything
+    pub notifications: Notifications,
+    // screen size, mouse and frame clock, swapped out to run without a window
+    pub backend: Box<dyn Backend>,
+    // seconds since controls.dat was last checked for outside edits
+    pub controls_check_timer: f32,
 }
 
 impl GameData {
 
+    /// Replace the in-memory controls with what is in controls.dat.
+    /// The current controls are kept if the file can't be loaded.
     pub fn reload_controls(&mut self) -> Result<(), String> {
         let control_handler = ControlHandler::load();
         if let Err(e) = control_handler {
             return Err(format!("Failed to load control handler: {}", e));
         }
-        self.control_handler = control_handler?;
+        let mut control_handler = control_handler?;
+        // the active context belongs to whatever is on screen, not the file
+        control_handler.set_context(self.control_handler.get_context());
+        self.control_handler = control_handler;
+        Ok(())
+    }
+
+    /// Run the fade between scenes, swapping in the new scene's world once the screen is black.
+    /// Scenes that have been visited before come back the way they were left.
+    pub fn update_scenes(&mut self, delta_seconds: f32) {
+        let Some((scene, spawn)) = self.scenes.update(delta_seconds) else {
+            return;
+        };
+
+        if scene == self.scenes.current {
+            self.world.place_player(spawn.as_deref());
+            self.camera.position = self.world.player.pos;
+            return;
+        }
+
+        let mut world = match self.scenes.take_visited(&scene) {
+            Some(w) => w,
+            None => match load_scene(&scene, &self.assets) {
+                Ok(w) => w,
+                Err(e) => {
+                    warn!("Can't go to scene {}: {}", scene, e);
+                    self.scenes.cancel();
+                    return;
+                }
+            },
+        };
+        // the player comes along as they are
+        world.player = self.world.player.clone();
+        world.place_player(spawn.as_deref());
+
+        let left_world = std::mem::replace(&mut self.world, world);
+        let left_scene = std::mem::replace(&mut self.scenes.current, scene);
+        info!("Left {} for {}", left_scene, self.scenes.current);
+        self.scenes.store_visited(left_scene, left_world);
+        // cut straight to the player rather than panning across the new map
+        self.camera.position = self.world.player.pos;
+
+        self.autosave();
+    }
+
+    /// Throw away the current world and every visited scene and start again from the first scene
+    pub fn new_game(&mut self) -> Result<(), String> {
+        let (scene, world) = scene::start_from_args(&self.assets)?;
+        self.world = world;
+        self.scenes = SceneManager::new(scene);
+        self.camera.position = self.world.player.pos;
+        Ok(())
+    }
+
+    pub fn save_game(&self, slot: SaveSlot, name: Option<&str>) -> Result<(), String> {
+        if get_scene(&self.scenes.current).is_none() {
+            return Err(format!("Can't save in {}, it isn't one of the game's scenes", self.scenes.current));
+        }
+        // maps loaded from disk with --map can't be rebuilt on load, so leave them out
+        let visited = self.scenes.visited().filter(|(id, _)| get_scene(id).is_some());
+        SaveGame::capture(name, &self.scenes.current, &self.world, visited).write(slot)?;
+        info!("Saved game to {}", slot);
+        Ok(())
+    }
+
+    /// Replace the world and every visited scene with a save. Nothing changes if the save can't be loaded.
+    pub fn load_game(&mut self, slot: SaveSlot) -> Result<(), String> {
+        let save = SaveGame::read(slot)?;
+        let (world, visited) = save.restore(&self.assets)?;
+
+        self.world = world;
+        self.scenes = SceneManager::new(save.scene.clone());
+        for (id, world) in visited {
+            self.scenes.store_visited(id, world);
+        }
+        self.camera.position = self.world.player.pos;
+        info!("Loaded game from {}", slot);
         Ok(())
     }
 
+    /// Save to the autosave slot, only warning if it fails since the game can carry on without it
+    pub fn autosave(&mut self) {
+        match self.save_game(SaveSlot::Auto, None) {
+            Ok(_) => self.notifications.push(PopupCard::new("Autosaved".to_string(), vec![], PopupLocation::BottomRight)),
+            Err(e) => warn!("Autosave failed: {}", e),
+        }
+    }
+
+    /// Roll a loot table and put what drops in the player's inventory,
+    /// with a popup listing what was found
+    pub fn drop_loot(&mut self, table: Option<&str>, title: String) {
+        let drops = match table {
+            Some(table) => self.loot.roll(table, &mut self.loot_rng),
+            None => Vec::new(),
+        };
+        let lines = self.collect(drops);
+        self.notifications.push(PopupCard::new(title, lines, PopupLocation::Bottom));
+    }
+
+    // add stacks to the inventory, returning a popup line for each
+    fn collect(&mut self, drops: Vec<ItemStack>) -> Vec<String> {
+        let mut lines = Vec::new();
+        for stack in drops {
+            let name = self.items.display_name(&stack.item);
+            let lost = self.world.player.inventory.add(&stack.item, stack.count, self.items.max_stack(&stack.item));
+            if lost < stack.count {
+                lines.push(format!("{}+{} {}", better_term::Color::BrightGreen, stack.count - lost, name));
+            }
+            if lost > 0 {
+                warn!("No room for {} {}", lost, stack.item);
+                lines.push(format!("{}No room for {} {}", better_term::Color::BrightRed, lost, name));
+            }
+        }
+        if lines.is_empty() {
+            lines.push(format!("{}Nothing found", better_term::Color::BrightBlack));
+        }
+        lines
+    }
+
+    /// Hot reload controls.dat if it was edited outside the game, checking about once a second.
+    /// Returns whether it was reloaded.
+    pub fn watch_controls(&mut self, delta_seconds: f32) -> bool {
+        self.controls_check_timer += delta_seconds;
+        if self.controls_check_timer < CONTROLS_CHECK_INTERVAL {
+            return false;
+        }
+        self.controls_check_timer = 0.0;
+        if !self.control_handler.changed_on_disk() {
+            return false;
+        }
+        match self.reload_controls() {
+            Ok(_) => {
+                info!("Reloaded controls from controls.dat");
+                self.notifications.push(PopupCard::new("Reloaded controls from controls.dat".to_string(), vec![], PopupLocation::Top));
+                true
+            }
+            Err(e) => {
+                // don't retry a broken file until it changes again
+                self.control_handler.mark_synced();
+                warn!("Keeping current controls: {}", e);
+                false
+            }
+        }
+    }
+
 }
\ No newline at end of file
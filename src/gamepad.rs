@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use crate::backend::Backend;
+
+/// Buttons use the Xbox layout, named by position so other controllers map naturally
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+pub enum GamepadButton {
+    South, // A / Cross
+    East, // B / Circle
+    West, // X / Square
+    North, // Y / Triangle
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    Guide,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 15] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::Guide,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+/// Stick axes rest at 0.0 and range from -1.0 (left / up) to 1.0 (right / down).
+/// Triggers rest at 0.0 and range to 1.0 when fully pressed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; 6] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+
+    pub fn is_trigger(&self) -> bool {
+        matches!(self, GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+/// A single physical input on a gamepad. Axes are split by direction so they can be bound like buttons.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+pub enum GamepadInput {
+    Button(GamepadButton),
+    Axis(GamepadAxis, AxisDirection),
+}
+
+#[derive(Debug, Default)]
+struct GamepadState {
+    device: Option<File>,
+    polls_since_connect_attempt: u32,
+    down: HashSet<GamepadButton>,
+    prev_down: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+    prev_axes: HashMap<GamepadAxis, f32>,
+}
+
+static STATE: Mutex<Option<GamepadState>> = Mutex::new(None);
+
+/// How many frames to wait between looking for a newly connected controller
+const RECONNECT_INTERVAL: u32 = 120;
+
+fn with_state<R>(f: impl FnOnce(&mut GamepadState) -> R) -> R {
+    let mut guard = STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(guard.get_or_insert_with(GamepadState::default))
+}
+
+/// Read everything the controller sent since the last frame. Must be called once per frame before input is checked.
+pub fn poll() {
+    with_state(|state| {
+        state.prev_down = state.down.clone();
+        state.prev_axes = state.axes.clone();
+
+        if state.device.is_none() {
+            if state.polls_since_connect_attempt > 0 {
+                state.polls_since_connect_attempt = (state.polls_since_connect_attempt + 1) % RECONNECT_INTERVAL;
+                return;
+            }
+            state.polls_since_connect_attempt = 1;
+            state.device = backend::open();
+            if state.device.is_some() {
+                crate::debug!("Gamepad connected");
+            }
+        }
+
+        let Some(device) = &mut state.device else {
+            return;
+        };
+        let mut down = std::mem::take(&mut state.down);
+        let mut axes = std::mem::take(&mut state.axes);
+        let connected = backend::read_events(device, &mut down, &mut axes);
+        state.down = down;
+        state.axes = axes;
+        if !connected {
+            crate::debug!("Gamepad disconnected");
+            state.device = None;
+            state.down.clear();
+            state.axes.clear();
+        }
+    })
+}
+
+// the raw state below is only for `WindowBackend`, everything else asks the backend so tests can script a pad
+
+pub fn is_connected() -> bool {
+    with_state(|state| state.device.is_some())
+}
+
+pub fn is_button_down(button: GamepadButton) -> bool {
+    with_state(|state| state.down.contains(&button))
+}
+
+pub fn is_button_pressed(button: GamepadButton) -> bool {
+    with_state(|state| state.down.contains(&button) && !state.prev_down.contains(&button))
+}
+
+pub fn is_button_released(button: GamepadButton) -> bool {
+    with_state(|state| !state.down.contains(&button) && state.prev_down.contains(&button))
+}
+
+pub fn axis_value(axis: GamepadAxis) -> f32 {
+    with_state(|state| state.axes.get(&axis).copied().unwrap_or(0.0))
+}
+
+pub fn last_axis_value(axis: GamepadAxis) -> f32 {
+    with_state(|state| state.prev_axes.get(&axis).copied().unwrap_or(0.0))
+}
+
+/// How far an axis is pushed in a direction, rescaled so the edge of the deadzone is 0.0 and full tilt is 1.0
+fn strength(value: f32, direction: AxisDirection, deadzone: f32) -> f32 {
+    let value = match direction {
+        AxisDirection::Positive => value,
+        AxisDirection::Negative => -value,
+    };
+    if value <= deadzone {
+        return 0.0;
+    }
+    ((value - deadzone) / (1.0 - deadzone).max(f32::EPSILON)).min(1.0)
+}
+
+pub fn axis_strength(backend: &dyn Backend, axis: GamepadAxis, direction: AxisDirection, deadzone: f32) -> f32 {
+    strength(backend.gamepad_axis(axis), direction, deadzone)
+}
+
+fn last_axis_strength(backend: &dyn Backend, axis: GamepadAxis, direction: AxisDirection, deadzone: f32) -> f32 {
+    strength(backend.last_gamepad_axis(axis), direction, deadzone)
+}
+
+/// The strength of any gamepad input, where buttons are either 0.0 or 1.0
+pub fn input_strength(backend: &dyn Backend, input: &GamepadInput, deadzone: f32) -> f32 {
+    match input {
+        GamepadInput::Button(b) => if backend.is_gamepad_button_down(*b) { 1.0 } else { 0.0 },
+        GamepadInput::Axis(axis, direction) => axis_strength(backend, *axis, *direction, deadzone),
+    }
+}
+
+pub fn is_input_down(backend: &dyn Backend, input: &GamepadInput, deadzone: f32) -> bool {
+    input_strength(backend, input, deadzone) > 0.0
+}
+
+pub fn is_input_pressed(backend: &dyn Backend, input: &GamepadInput, deadzone: f32) -> bool {
+    match input {
+        GamepadInput::Button(b) => backend.is_gamepad_button_pressed(*b),
+        GamepadInput::Axis(axis, direction) => axis_strength(backend, *axis, *direction, deadzone) > 0.0
+            && last_axis_strength(backend, *axis, *direction, deadzone) <= 0.0,
+    }
+}
+
+pub fn is_input_released(backend: &dyn Backend, input: &GamepadInput, deadzone: f32) -> bool {
+    match input {
+        GamepadInput::Button(b) => backend.is_gamepad_button_released(*b),
+        GamepadInput::Axis(axis, direction) => axis_strength(backend, *axis, *direction, deadzone) <= 0.0
+            && last_axis_strength(backend, *axis, *direction, deadzone) > 0.0,
+    }
+}
+
+/// Every input that started being pressed this frame, used when capturing a new binding
+pub fn get_inputs_pressed(backend: &dyn Backend, stick_deadzone: f32, trigger_deadzone: f32) -> Vec<GamepadInput> {
+    let mut pressed = GamepadButton::ALL.iter()
+        .filter(|b| backend.is_gamepad_button_pressed(**b))
+        .map(|b| GamepadInput::Button(*b))
+        .collect::<Vec<GamepadInput>>();
+
+    for axis in GamepadAxis::ALL {
+        let deadzone = if axis.is_trigger() { trigger_deadzone } else { stick_deadzone };
+        for direction in [AxisDirection::Positive, AxisDirection::Negative] {
+            let input = GamepadInput::Axis(axis, direction);
+            if is_input_pressed(backend, &input, deadzone) {
+                pressed.push(input);
+            }
+        }
+    }
+
+    pressed
+}
+
+pub fn input_to_string(input: &GamepadInput) -> String {
+    match input {
+        GamepadInput::Button(b) => match b {
+            GamepadButton::South => "PadA",
+            GamepadButton::East => "PadB",
+            GamepadButton::West => "PadX",
+            GamepadButton::North => "PadY",
+            GamepadButton::LeftBumper => "PadLB",
+            GamepadButton::RightBumper => "PadRB",
+            GamepadButton::Select => "PadSelect",
+            GamepadButton::Start => "PadStart",
+            GamepadButton::Guide => "PadGuide",
+            GamepadButton::LeftStick => "PadLS",
+            GamepadButton::RightStick => "PadRS",
+            GamepadButton::DPadUp => "PadUp",
+            GamepadButton::DPadDown => "PadDown",
+            GamepadButton::DPadLeft => "PadLeft",
+            GamepadButton::DPadRight => "PadRight",
+        }.to_string(),
+        GamepadInput::Axis(axis, direction) => {
+            let name = match axis {
+                GamepadAxis::LeftStickX | GamepadAxis::LeftStickY => "LStick",
+                GamepadAxis::RightStickX | GamepadAxis::RightStickY => "RStick",
+                GamepadAxis::LeftTrigger => return "PadLT".to_string(),
+                GamepadAxis::RightTrigger => return "PadRT".to_string(),
+            };
+            let dir = match (axis, direction) {
+                (GamepadAxis::LeftStickX | GamepadAxis::RightStickX, AxisDirection::Negative) => "Left",
+                (GamepadAxis::LeftStickX | GamepadAxis::RightStickX, AxisDirection::Positive) => "Right",
+                (_, AxisDirection::Negative) => "Up",
+                (_, AxisDirection::Positive) => "Down",
+            };
+            format!("{}{}", name, dir)
+        }
+    }
+}
+
+/// The inverse of `input_to_string`
+pub fn string_to_input(name: &str) -> Option<GamepadInput> {
+    let buttons = GamepadButton::ALL.iter().map(|b| GamepadInput::Button(*b));
+    // positive first so "PadLT" reads back as the trigger being pulled
+    let axes = GamepadAxis::ALL.iter().flat_map(|axis| [
+        GamepadInput::Axis(*axis, AxisDirection::Positive),
+        GamepadInput::Axis(*axis, AxisDirection::Negative),
+    ]);
+    buttons.chain(axes).find(|input| input_to_string(input) == name)
+}
+
+/// Reads the Linux joystick interface (`/dev/input/js*`), which every common controller driver exposes
+#[cfg(target_os = "linux")]
+mod backend {
+    use std::collections::{HashMap, HashSet};
+    use std::fs::{File, OpenOptions};
+    use std::io::{ErrorKind, Read};
+    use std::os::unix::fs::OpenOptionsExt;
+    use super::{GamepadAxis, GamepadButton};
+
+    const JS_EVENT_BUTTON: u8 = 0x01;
+    const JS_EVENT_AXIS: u8 = 0x02;
+    const JS_EVENT_INIT: u8 = 0x80;
+
+    pub fn open() -> Option<File> {
+        (0..4).find_map(|i| OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(format!("/dev/input/js{}", i))
+            .ok())
+    }
+
+    /// returns false if the device has been disconnected
+    pub fn read_events(device: &mut File, down: &mut HashSet<GamepadButton>, axes: &mut HashMap<GamepadAxis, f32>) -> bool {
+        // struct js_event { u32 time; i16 value; u8 type; u8 number; }
+        let mut event = [0u8; 8];
+        loop {
+            match device.read(&mut event) {
+                Ok(8) => {}
+                // events are read whole, so anything shorter means the device went away mid-event.
+                // treating it as a disconnect drops the half event and reopens the device
+                Ok(_) => return false,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return true,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return false,
+            }
+            let value = i16::from_le_bytes([event[4], event[5]]);
+            let kind = event[6] & !JS_EVENT_INIT;
+            let number = event[7];
+
+            match kind {
+                JS_EVENT_BUTTON => {
+                    let Some(button) = button_from_number(number) else {
+                        continue;
+                    };
+                    if value != 0 {
+                        down.insert(button);
+                    } else {
+                        down.remove(&button);
+                    }
+                }
+                JS_EVENT_AXIS => {
+                    let normalized = value as f32 / i16::MAX as f32;
+                    match number {
+                        // the dpad is reported as a pair of axes
+                        6 => set_dpad(down, normalized, GamepadButton::DPadLeft, GamepadButton::DPadRight),
+                        7 => set_dpad(down, normalized, GamepadButton::DPadUp, GamepadButton::DPadDown),
+                        _ => {
+                            let Some(axis) = axis_from_number(number) else {
+                                continue;
+                            };
+                            let normalized = if axis.is_trigger() {
+                                // triggers rest at -1.0
+                                (normalized + 1.0) / 2.0
+                            } else {
+                                normalized
+                            };
+                            axes.insert(axis, normalized.clamp(-1.0, 1.0));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_dpad(down: &mut HashSet<GamepadButton>, value: f32, negative: GamepadButton, positive: GamepadButton) {
+        down.remove(&negative);
+        down.remove(&positive);
+        if value < -0.5 {
+            down.insert(negative);
+        } else if value > 0.5 {
+            down.insert(positive);
+        }
+    }
+
+    fn button_from_number(number: u8) -> Option<GamepadButton> {
+        Some(match number {
+            0 => GamepadButton::South,
+            1 => GamepadButton::East,
+            2 => GamepadButton::West,
+            3 => GamepadButton::North,
+            4 => GamepadButton::LeftBumper,
+            5 => GamepadButton::RightBumper,
+            6 => GamepadButton::Select,
+            7 => GamepadButton::Start,
+            8 => GamepadButton::Guide,
+            9 => GamepadButton::LeftStick,
+            10 => GamepadButton::RightStick,
+            _ => return None,
+        })
+    }
+
+    fn axis_from_number(number: u8) -> Option<GamepadAxis> {
+        Some(match number {
+            0 => GamepadAxis::LeftStickX,
+            1 => GamepadAxis::LeftStickY,
+            2 => GamepadAxis::LeftTrigger,
+            3 => GamepadAxis::RightStickX,
+            4 => GamepadAxis::RightStickY,
+            5 => GamepadAxis::RightTrigger,
+            _ => return None,
+        })
+    }
+}
+
+/// Controllers are only supported on Linux. Everywhere else no controller is ever found,
+/// so `is_connected` stays false and the game is played with keyboard and mouse.
+#[cfg(not(target_os = "linux"))]
+mod backend {
+    use std::collections::{HashMap, HashSet};
+    use std::fs::File;
+    use super::{GamepadAxis, GamepadButton};
+
+    pub fn open() -> Option<File> {
+        None
+    }
+
+    pub fn read_events(_device: &mut File, _down: &mut HashSet<GamepadButton>, _axes: &mut HashMap<GamepadAxis, f32>) -> bool {
+        false
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use macroquad::color::Color;
+use macroquad::math::vec2;
+use macroquad::window::clear_background;
+use crate::controls::InputContext;
+use crate::error::GameError;
+use crate::gamedata::GameData;
+use crate::gamestate::playing::PlayingGS;
+use crate::overlay::settings::SettingsOverlay;
+use crate::save;
+use crate::ui::widgets::{Ui, UiInput, UiState};
+use crate::warn;
+use super::{GameState, GameStateAction};
+
+const BUTTON_LABELS: [&str; 4] = ["New Game", "Continue", "Settings", "Quit"];
+const CONTINUE: usize = 1;
+
+// room for the buttons and a message under them
+const PANEL_SIZE: (f32, f32) = (260.0, 212.0);
+
+/// The first thing shown after loading
+#[derive(Clone, Debug)]
+pub struct MainMenuGS {
+    ui: UiState,
+    // continue is disabled without a save to continue from
+    can_continue: bool,
+    // why the last new game or continue didn't work
+    message: Option<String>,
+}
+
+impl MainMenuGS {
+    pub fn new() -> Result<Box<Self>, GameError> {
+        Ok(Box::new(Self {
+            ui: UiState::default(),
+            can_continue: save::latest_slot().is_some(),
+            message: None,
+        }))
+    }
+
+    fn activate(&mut self, index: usize, data: &mut GameData) -> Result<GameStateAction, GameError> {
+        match index {
+            0 => {
+                if let Err(e) = data.new_game() {
+                    warn!("Failed to start a new game: {}", e);
+                    self.message = Some("Failed to start a new game".to_string());
+                    return Ok(GameStateAction::NoOp);
+                }
+                Ok(GameStateAction::ChangeState(PlayingGS::new()?))
+            }
+            CONTINUE => {
+                let Some(slot) = save::latest_slot() else {
+                    return Ok(GameStateAction::NoOp);
+                };
+                if let Err(e) = data.load_game(slot) {
+                    warn!("Failed to continue from {}: {}", slot, e);
+                    self.message = Some(format!("{} couldn't be loaded", slot));
+                    return Ok(GameStateAction::NoOp);
+                }
+                Ok(GameStateAction::ChangeState(PlayingGS::new()?))
+            }
+            2 => Ok(GameStateAction::SpawnOverlay(Box::new(SettingsOverlay::new()))),
+            3 => Ok(GameStateAction::Quit),
+            _ => Ok(GameStateAction::NoOp),
+        }
+    }
+}
+
+impl GameState for MainMenuGS {
+
+    fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<GameStateAction, GameError> {
+        let actions = data.control_handler.get_actions(data.backend.as_ref());
+        let input = UiInput::new(actions, data);
+        let mut ui = Ui::begin(&mut self.ui, input, data.settings.ui_scale, delta_time.as_secs_f32());
+        ui.panel(vec2(PANEL_SIZE.0, PANEL_SIZE.1), "Monkey Business");
+        let mut pressed = None;
+        for (i, label) in BUTTON_LABELS.iter().enumerate() {
+            if ui.button_enabled(label, i != CONTINUE || self.can_continue) {
+                pressed = Some(i);
+            }
+        }
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        ui.end();
+
+        match pressed {
+            Some(index) => self.activate(index, data),
+            None => Ok(GameStateAction::NoOp),
+        }
+    }
+
+    fn persistent_update(&mut self, _delta_time: &Duration, _data: &mut GameData) -> Result<GameStateAction, GameError> {
+        Ok(GameStateAction::NoOp)
+    }
+
+    fn pause(&mut self, _data: &mut GameData) -> Result<(), GameError> {
+        Ok(())
+    }
+
+    fn restore(&mut self, _data: &mut GameData) -> Result<(), GameError> {
+        Ok(())
+    }
+
+    fn draw(&mut self, _delta_time: &Duration, data: &mut GameData) -> Result<(), GameError> {
+        clear_background(Color::from_hex(0x453e3d));
+        self.ui.draw(&data.assets);
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        "Main Menu".to_string()
+    }
+
+    fn input_context(&self) -> InputContext {
+        InputContext::Menu
+    }
+
+}
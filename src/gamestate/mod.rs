@@ -1,14 +1,20 @@
 use std::fmt::Debug;
 use std::time::Duration;
+use crate::controls::InputContext;
 use crate::error::GameError;
 use crate::gamedata::GameData;
 use crate::overlay::Overlay;
 
+pub mod main_menu;
 pub mod playing;
 
 pub enum GameStateAction {
     ChangeState(Box<dyn GameState>),
     SpawnOverlay(Box<dyn Overlay>),
+    /// Fade over to another scene, arriving at the named spawn point
+    ChangeScene { scene: String, spawn: Option<String> },
+    /// Close the game
+    Quit,
     NoOp,
 }
 
@@ -17,13 +23,24 @@ pub trait GameState: Debug {
     /// updates when the game is active
     /// Great for key presses, mouse movement, etc.
     fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<GameStateAction, GameError>;
+    /// Runs at a fixed rate while the game is active, after `update` has read the frame's input.
+    /// Simulation belongs here so it behaves the same at any frame rate.
+    fn fixed_update(&mut self, _step: &Duration, _data: &mut GameData) -> Result<(), GameError> {
+        Ok(())
+    }
     /// Will always be called, even when an overlay is shown
     fn persistent_update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<GameStateAction, GameError>;
     fn pause(&mut self, data: &mut GameData) -> Result<(), GameError>;
     fn restore(&mut self, data: &mut GameData) -> Result<(), GameError>;
     fn draw(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<(), GameError>;
+    /// Called once before the game closes, whether from a menu or the window being closed
+    fn quit(&mut self, _data: &mut GameData) {}
     fn get_name(&self) -> String;
     fn is_overlay(&self) -> bool {
         false
     }
+    /// The set of actions this gamestate listens for when no overlay is open
+    fn input_context(&self) -> InputContext {
+        InputContext::Gameplay
+    }
 }
\ No newline at end of file
@@ -1,19 +1,21 @@
 use std::time::Duration;
 
-use macroquad::{color::Color, math::vec2, window::clear_background};
-use macroquad::prelude::{draw_text_ex, measure_text, screen_width};
+use macroquad::{color::Color, math::{vec2, Vec2}, window::clear_background};
+use macroquad::prelude::{draw_rectangle, draw_text_ex, measure_text};
 use macroquad::text::TextParams;
 use crate::controls::Action;
 use crate::error::GameError;
 use crate::gamedata::GameData;
+use crate::overlay::inventory::InventoryOverlay;
 use crate::overlay::pause::PauseOverlay;
-use crate::world::player::PlayerFacing;
 use crate::util::{draw_ansi_text, remove_ansii_escape_codes};
 use super::{GameState, GameStateAction};
 
 #[derive(Clone, Debug)]
 pub struct PlayingGS {
     paused: bool,
+    // the movement asked for this frame, applied by each fixed update
+    movement: Vec2,
     debug: bool, // todo: move this to gamedata
 }
 
@@ -21,6 +23,7 @@ impl PlayingGS {
     pub fn new() -> Result<Box<Self>, GameError> {
         Ok(Box::new(Self {
             paused: false,
+            movement: Vec2::ZERO,
             debug: false,
         }))
     }
@@ -29,77 +32,39 @@ impl PlayingGS {
 impl GameState for PlayingGS {
 
     fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<GameStateAction, GameError> {
+        // hold still while fading between scenes
+        data.update_scenes(delta_time.as_secs_f32());
+        if data.scenes.is_transitioning() {
+            self.movement = Vec2::ZERO;
+            return Ok(GameStateAction::NoOp);
+        }
 
         // make the player rotate towards the mouse
         // not top down anymore
         //self.player.look_towards_mouse();
 
-        let sprinting_toggled = data.control_handler.is_sprint_toggle();
-        if !sprinting_toggled {
-            // if sprinting is not in toggle mode, it will be set to true each frame only if the button is pressed
-            // this is a limitation of Banana Engine that should be addressed in the future, possibly with a control handler rework
-            data.world.player.sprinting = false;
-        }
-
         // handle input and make the player respond accordingly
-        let actions = data.control_handler.get_actions();
-        let mut movement = vec2(0.0, 0.0);
-        // handle various movement types
+        let actions = data.control_handler.get_actions(data.backend.as_ref());
+        self.movement = data.world.player.steer(&actions, &data.control_handler, data.backend.as_ref());
         for action in actions {
             match action {
-                // todo: add limits like obstacles
-                Action::MoveUp => {
-                    movement.y -= 1.0;
-                    data.world.player.facing = match data.world.player.facing {
-                        PlayerFacing::UpRight => PlayerFacing::UpRight,
-                        PlayerFacing::UpLeft => PlayerFacing::UpLeft,
-                        PlayerFacing::DownLeft => PlayerFacing::UpLeft,
-                        PlayerFacing::DownRight => PlayerFacing::UpRight,
-                    };
-                }
-                Action::MoveDown => {
-                    movement.y += 1.0;
-                    data.world.player.facing = match data.world.player.facing {
-                        PlayerFacing::UpRight => PlayerFacing::DownRight,
-                        PlayerFacing::UpLeft => PlayerFacing::DownLeft,
-                        PlayerFacing::DownLeft => PlayerFacing::DownLeft,
-                        PlayerFacing::DownRight => PlayerFacing::DownRight,
-                    };
-                }
-                Action::MoveLeft => {
-                    movement.x -= 1.0;
-                    data.world.player.facing = match data.world.player.facing {
-                        PlayerFacing::UpRight => PlayerFacing::UpLeft,
-                        PlayerFacing::UpLeft => PlayerFacing::UpLeft,
-                        PlayerFacing::DownLeft => PlayerFacing::DownLeft,
-                        PlayerFacing::DownRight => PlayerFacing::DownLeft,
-                    };
-                }
-                Action::MoveRight => {
-                    movement.x += 1.0;
-                    data.world.player.facing = match data.world.player.facing {
-                        PlayerFacing::UpRight => PlayerFacing::UpRight,
-                        PlayerFacing::UpLeft => PlayerFacing::UpRight,
-                        PlayerFacing::DownLeft => PlayerFacing::DownRight,
-                        PlayerFacing::DownRight => PlayerFacing::DownRight,
-                    };
-                }
                 Action::Interact => {
-                    if let Some(id) = data.world.is_click_on_interactable(data) {
+                    // without a mouse, interact with whatever is closest instead of what's under the cursor
+                    let target = if data.control_handler.is_gamepad_action_active(&Action::Interact, data.backend.as_ref()) {
+                        data.world.closest_interactable_in_reach(data)
+                    } else {
+                        data.world.is_click_on_interactable(data)
+                    };
+                    if let Some(id) = target {
                         if let Some(interactable) = data.world.get_mut_interactable_by_id(id) { // First mutable borrow
                             return interactable.interact(); // second borrow, fails both when immutable and mutable
                         }
                     }
                 }
-                Action::Sprint => {
-                    if sprinting_toggled {
-                        data.world.player.sprinting = !data.world.player.sprinting;
-                    } else {
-                        data.world.player.sprinting = true;
-                    }
-                }
+                Action::ZoomIn => data.camera.zoom_in(),
+                Action::ZoomOut => data.camera.zoom_out(),
                 Action::Inventory => {
-                    // todo: add an inventory system and open it here
+                    return Ok(GameStateAction::SpawnOverlay(Box::new(InventoryOverlay::new())));
                 }
                 Action::BasicAttack => {
                     // todo: add attacks
@@ -114,12 +79,18 @@ impl GameState for PlayingGS {
                 _ => { /* Other actions are not used here */ }
             }
         }
-        let player_sprite = data.assets.player_sprite.size();
-        data.world.player.apply_movement(player_sprite, movement, &data.world.interactables, delta_time.as_millis());
 
         Ok(GameStateAction::NoOp)
     }
 
+    fn fixed_update(&mut self, step: &Duration, data: &mut GameData) -> Result<(), GameError> {
+        let player_sprite = data.assets.player_sprite.size();
+        data.world.fixed_update(self.movement, player_sprite, step.as_secs_f32());
+        // the camera eases after the player at the same fixed rate, so it moves the same however fast frames are drawn
+        data.camera.update(data.world.player.pos, data.world.size, step.as_secs_f32(), data.backend.screen_size());
+        Ok(())
+    }
+
     fn persistent_update(&mut self, _delta_time: &Duration, _data: &mut GameData) -> Result<GameStateAction, GameError> {
         // not yet implemented
         Ok(GameStateAction::NoOp)
@@ -127,14 +98,11 @@ impl GameState for PlayingGS {
 
     fn pause(&mut self, _data: &mut GameData) -> Result<(), GameError> {
         self.paused = true;
+        self.movement = Vec2::ZERO;
         Ok(())
     }
 
-    fn restore(&mut self, data: &mut GameData) -> Result<(), GameError> {
-        // Refresh everything that needs to be
-        if let Err(e) = data.reload_controls() {
-            return Err(GameError::Update(format!("Failed to reload controls: {}", e)));
-        }
+    fn restore(&mut self, _data: &mut GameData) -> Result<(), GameError> {
         self.paused = false;
         Ok(())
     }
@@ -143,22 +111,48 @@ impl GameState for PlayingGS {
         // clear the background and give a default color
         clear_background(Color::from_hex(0x453e3d));
 
-        // draw the player
-        data.world.draw_player(data);
+        // the map goes under everything else
+        data.world.draw_map(data);
 
         // update the interactable's animation frames
         if let Err(e) = data.world.update_interactables(delta_time.as_millis() as f32) {
             return Err(GameError::Update(format!("Failed to update interactables: {}", e)));
         }
 
-        // draw the interactables
-        data.world.draw_interactables(data)?;
+        // draw the player and interactables in depth order
+        data.world.draw_entities(data)?;
+
+        // fade to black between scenes
+        let fade = data.scenes.fade();
+        if fade > 0.0 {
+            let screen = data.backend.screen_size();
+            draw_rectangle(0.0, 0.0, screen.x, screen.y, Color::new(0.0, 0.0, 0.0, fade));
+        }
+
+        if data.settings.show_fps && !self.debug {
+            let fps = format!("FPS: {}", data.fps.round());
+            let text_size = measure_text(&fps, Some(&data.assets.font), 8, 1.0);
+            draw_text_ex(
+                &fps,
+                4.0,
+                text_size.height + 4.0,
+                TextParams {
+                    font: Some(&data.assets.font),
+                    font_size: 8,
+                    color: Color::from_rgba(255, 255, 255, 180),
+                    ..Default::default()
+                },
+            );
+        }
 
         if self.debug {
             let spacing = 4.0;
             let debug_info = vec![
                 format!("FPS: {}", data.fps.round()),
                 format!("Player Pos: {}", data.world.player.pos.round()),
+                format!("Tile: {:?}", data.world.map.grid.tile_at(data.world.player.pos)),
+                format!("Scene: {}", data.scenes.current),
+                format!("Camera: {} x{:.2}", data.camera.position.round(), data.camera.zoom()),
                 format!("Paused: {}", self.paused),
                 format!("Sprinting: {}", data.world.player.sprinting),
                 format!("Facing: {:?}", data.world.player.facing),
@@ -200,20 +194,24 @@ impl GameState for PlayingGS {
             let text_size = measure_text(&raw_ansi_test, Some(&data.assets.font), 8, 1.0);
             draw_ansi_text(
                 &ansi_test,
-                vec2(screen_width() - (text_size.width + spacing), text_size.height + spacing),
+                vec2(data.backend.screen_size().x - (text_size.width + spacing), text_size.height + spacing),
                 &data.assets,
                 8,
                 4.0,
             );
         }
 
-        if !self.paused {
+        if !self.paused && !data.scenes.is_transitioning() && data.settings.show_tooltips {
             data.world.handle_tooltips(data);
         }
 
         Ok(())
     }
 
+    fn quit(&mut self, data: &mut GameData) {
+        data.autosave();
+    }
+
     fn get_name(&self) -> String {
         "Playing".to_string()
     }
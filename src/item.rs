@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use macroquad::color::WHITE;
+use macroquad::math::{vec2, Rect};
+use macroquad::texture::{draw_texture_ex, DrawTextureParams};
+use serde::Deserialize;
+use crate::assets::GlobalAssets;
+use crate::world::inventory::DEFAULT_MAX_STACK;
+
+/// The item catalog that ships with the game
+pub const ITEMS_FILE: &str = include_str!("../assets/data/items.json");
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    pub fn color(&self) -> better_term::Color {
+        match self {
+            Rarity::Common => better_term::Color::White,
+            Rarity::Uncommon => better_term::Color::BrightGreen,
+            Rarity::Rare => better_term::Color::BrightBlue,
+            Rarity::Epic => better_term::Color::BrightPurple,
+            Rarity::Legendary => better_term::Color::BrightYellow,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Rarity::Common => "Common",
+            Rarity::Uncommon => "Uncommon",
+            Rarity::Rare => "Rare",
+            Rarity::Epic => "Epic",
+            Rarity::Legendary => "Legendary",
+        }
+    }
+}
+
+/// Where an item's icon is on a sprite sheet, in pixels
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpriteRegion {
+    /// file name of the sheet, like tileset images in maps
+    pub sheet: String,
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+/// What a tool does when mining
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolStats {
+    /// rocks up to this tier can be mined with it
+    pub tier: u32,
+    /// hits taken off a rock per click
+    pub damage: u32,
+    /// from 0 to 1, how often a click does double damage
+    #[serde(default)]
+    pub crit_chance: f32,
+}
+
+/// Mining with no tool equipped
+pub const FISTS: ToolStats = ToolStats { tier: 0, damage: 1, crit_chance: 0.0 };
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemDef {
+    pub id: String,
+    pub name: String,
+    /// "#rrggbb" to color the name, otherwise it's colored by rarity
+    #[serde(default)]
+    pub color: Option<String>,
+    pub sprite: SpriteRegion,
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+    #[serde(default)]
+    pub rarity: Rarity,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// set for items that can be equipped to mine with
+    #[serde(default)]
+    pub tool: Option<ToolStats>,
+}
+
+fn default_max_stack() -> u32 {
+    DEFAULT_MAX_STACK
+}
+
+impl ItemDef {
+    /// The name with its ANSI color, for tooltips and popups
+    pub fn display_name(&self) -> String {
+        match self.color.as_deref().and_then(parse_hex) {
+            Some((r, g, b)) => format!("\x1b[38;2;{};{};{}m{}", r, g, b, self.name),
+            None => format!("{}{}", self.rarity.color(), self.name),
+        }
+    }
+
+    pub fn draw(&self, rect: Rect, assets: &GlobalAssets) {
+        let Some(texture) = assets.tileset_texture(&self.sprite.sheet) else {
+            return;
+        };
+        let region = &self.sprite;
+        draw_texture_ex(&texture, rect.x, rect.y, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(rect.w, rect.h)),
+            source: Some(Rect::new(region.x, region.y, region.w, region.h)),
+            ..Default::default()
+        });
+    }
+
+    fn validate(&self, assets: &GlobalAssets) -> Result<(), String> {
+        if self.id.is_empty() || !self.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            return Err(format!("id `{}` should be lowercase letters, digits and underscores", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err("name is empty".to_string());
+        }
+        if self.max_stack == 0 {
+            return Err("max_stack must be at least 1".to_string());
+        }
+        if let Some(color) = &self.color {
+            if parse_hex(color).is_none() {
+                return Err(format!("color `{}` isn't a #rrggbb hex color", color));
+            }
+        }
+        if let Some(tool) = &self.tool {
+            if tool.damage == 0 {
+                return Err("tool damage must be at least 1".to_string());
+            }
+            if !(0.0..=1.0).contains(&tool.crit_chance) {
+                return Err(format!("tool crit_chance {} should be from 0 to 1", tool.crit_chance));
+            }
+        }
+        let region = &self.sprite;
+        let Some(texture) = assets.tileset_texture(&region.sheet) else {
+            return Err(format!("unknown sprite sheet `{}`", region.sheet));
+        };
+        if region.w <= 0.0 || region.h <= 0.0 || region.x < 0.0 || region.y < 0.0
+            || region.x + region.w > texture.width() || region.y + region.h > texture.height() {
+            return Err(format!("sprite region {}x{} at ({}, {}) is outside {} ({}x{})",
+                               region.w, region.h, region.x, region.y, region.sheet, texture.width(), texture.height()));
+        }
+        Ok(())
+    }
+}
+
+/// "#rrggbb" as red, green and blue
+pub fn parse_hex(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[derive(Deserialize)]
+struct ItemsFile {
+    items: Vec<serde_json::Value>,
+}
+
+/// Every item the game knows about, by id
+#[derive(Clone, Debug, Default)]
+pub struct ItemRegistry {
+    items: HashMap<String, ItemDef>,
+}
+
+impl ItemRegistry {
+    /// Parse an items file. Entries that don't make sense are left out, and each one comes back as
+    /// an error so the rest of the catalog still loads. Only a file that can't be read at all fails.
+    pub fn from_json(json: &str, assets: &GlobalAssets) -> Result<(Self, Vec<String>), String> {
+        let file = match serde_json::from_str::<ItemsFile>(json) {
+            Ok(f) => f,
+            Err(e) => return Err(format!("Items file is invalid: {}", e)),
+        };
+
+        let mut registry = Self::default();
+        let mut errors = Vec::new();
+        for (i, entry) in file.items.into_iter().enumerate() {
+            // name the entry by id when there is one, the index otherwise
+            let label = match entry.get("id").and_then(|id| id.as_str()) {
+                Some(id) => format!("Item {} (`{}`)", i, id),
+                None => format!("Item {}", i),
+            };
+            let def = match serde_json::from_value::<ItemDef>(entry) {
+                Ok(d) => d,
+                Err(e) => {
+                    errors.push(format!("{}: {}", label, e));
+                    continue;
+                }
+            };
+            if let Err(e) = def.validate(assets) {
+                errors.push(format!("{}: {}", label, e));
+                continue;
+            }
+            if registry.items.contains_key(&def.id) {
+                errors.push(format!("{}: id is already used by an earlier item", label));
+                continue;
+            }
+            registry.items.insert(def.id.clone(), def);
+        }
+        Ok((registry, errors))
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ItemDef> {
+        self.items.get(id)
+    }
+
+    pub fn count(&self) -> usize {
+        self.items.len()
+    }
+
+    /// How many fit in one inventory slot, the default for items that aren't in the registry
+    pub fn max_stack(&self, id: &str) -> u32 {
+        self.get(id).map(|d| d.max_stack).unwrap_or(DEFAULT_MAX_STACK)
+    }
+
+    /// The weakest tool that can mine rocks of a tier, for telling the player what they need
+    pub fn tool_for_tier(&self, tier: u32) -> Option<&ItemDef> {
+        self.items.values()
+            .filter(|d| d.tool.as_ref().is_some_and(|t| t.tier >= tier))
+            .min_by_key(|d| d.tool.as_ref().map(|t| (t.tier, t.damage)))
+    }
+
+    /// The colored name of an item, or its id if it isn't in the registry
+    pub fn display_name(&self, id: &str) -> String {
+        self.get(id).map(|d| d.display_name()).unwrap_or_else(|| id.to_string())
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use crate::item::ItemRegistry;
+use crate::replay;
+use crate::world::inventory::ItemStack;
+
+/// The loot tables that ship with the game
+pub const LOOT_FILE: &str = include_str!("../assets/data/loot.json");
+
+// tables can't nest deeper than this, which also stops a table from rolling itself forever
+const MAX_DEPTH: usize = 8;
+
+fn one() -> u32 {
+    1
+}
+
+/// One possible outcome of a roll: an item, another table to roll on, or nothing if it has neither
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LootEntry {
+    #[serde(default)]
+    pub item: Option<String>,
+    #[serde(default)]
+    pub table: Option<String>,
+    #[serde(default = "one")]
+    pub min: u32,
+    #[serde(default = "one")]
+    pub max: u32,
+    /// how likely this entry is compared to the others in the same table
+    #[serde(default = "one")]
+    pub weight: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rolls {
+    #[serde(default = "one")]
+    pub min: u32,
+    #[serde(default = "one")]
+    pub max: u32,
+}
+
+impl Default for Rolls {
+    fn default() -> Self {
+        Self { min: 1, max: 1 }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LootTable {
+    /// dropped every time, on top of the rolls
+    #[serde(default)]
+    pub guaranteed: Vec<LootEntry>,
+    /// how many times to pick a weighted entry
+    #[serde(default)]
+    pub rolls: Rolls,
+    #[serde(default)]
+    pub entries: Vec<LootEntry>,
+}
+
+#[derive(Deserialize)]
+struct LootFile {
+    #[serde(default)]
+    interactables: HashMap<String, String>,
+    tables: HashMap<String, serde_json::Value>,
+}
+
+/// Every loot table by name, and which table each type of interactable drops from
+#[derive(Clone, Debug, Default)]
+pub struct LootTables {
+    tables: HashMap<String, LootTable>,
+    interactables: HashMap<String, String>,
+}
+
+impl LootTables {
+    /// Parse a loot file, checking every item and nested table exists.
+    /// Broken tables are left out and come back as errors, like items.
+    pub fn from_json(json: &str, items: &ItemRegistry) -> Result<(Self, Vec<String>), String> {
+        let file = match serde_json::from_str::<LootFile>(json) {
+            Ok(f) => f,
+            Err(e) => return Err(format!("Loot file is invalid: {}", e)),
+        };
+
+        let mut errors = Vec::new();
+        let mut tables = HashMap::new();
+        for (name, value) in file.tables {
+            match serde_json::from_value::<LootTable>(value) {
+                Ok(table) => {
+                    tables.insert(name, table);
+                }
+                Err(e) => errors.push(format!("Table `{}`: {}", name, e)),
+            }
+        }
+
+        // drop bad tables until nothing else breaks, since a table is broken if one it nests is
+        loop {
+            let broken = tables.iter()
+                .find_map(|(name, table)| Self::validate(name, table, &tables, items).err().map(|e| (name.clone(), e)));
+            let Some((name, error)) = broken else {
+                break;
+            };
+            tables.remove(&name);
+            errors.push(format!("Table `{}`: {}", name, error));
+        }
+
+        let mut interactables = HashMap::new();
+        for (ty, table) in file.interactables {
+            if tables.contains_key(&table) {
+                interactables.insert(ty, table);
+            } else {
+                errors.push(format!("`{}` drops from table `{}`, which doesn't exist", ty, table));
+            }
+        }
+
+        Ok((Self { tables, interactables }, errors))
+    }
+
+    fn validate(name: &str, table: &LootTable, tables: &HashMap<String, LootTable>, items: &ItemRegistry) -> Result<(), String> {
+        if table.rolls.min > table.rolls.max {
+            return Err(format!("rolls min {} is more than max {}", table.rolls.min, table.rolls.max));
+        }
+        if table.rolls.max > 0 && table.entries.iter().map(|e| e.weight).sum::<u32>() == 0 {
+            return Err("has rolls but no weighted entries".to_string());
+        }
+        for entry in table.guaranteed.iter().chain(&table.entries) {
+            match (&entry.item, &entry.table) {
+                (Some(_), Some(_)) => return Err("an entry has both an item and a table".to_string()),
+                (Some(item), None) if items.get(item).is_none() => return Err(format!("unknown item `{}`", item)),
+                (None, Some(nested)) if !tables.contains_key(nested) => return Err(format!("unknown table `{}`", nested)),
+                _ => {}
+            }
+            if entry.min > entry.max {
+                return Err(format!("an entry's min {} is more than its max {}", entry.min, entry.max));
+            }
+        }
+        if Self::nests(name, name, tables, 0) {
+            return Err(format!("rolls on itself, or nests more than {} deep", MAX_DEPTH));
+        }
+        Ok(())
+    }
+
+    // whether `table` leads back to `target`, or goes on too long to tell
+    fn nests(target: &str, table: &str, tables: &HashMap<String, LootTable>, depth: usize) -> bool {
+        if depth >= MAX_DEPTH {
+            return true;
+        }
+        let Some(table) = tables.get(table) else {
+            return false;
+        };
+        table.guaranteed.iter().chain(&table.entries)
+            .filter_map(|e| e.table.as_deref())
+            .any(|nested| nested == target || Self::nests(target, nested, tables, depth + 1))
+    }
+
+    pub fn count(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn contains(&self, table: &str) -> bool {
+        self.tables.contains_key(table)
+    }
+
+    /// The table an interactable type drops from
+    pub fn table_for(&self, interactable_type: &str) -> Option<&str> {
+        self.interactables.get(interactable_type).map(|t| t.as_str())
+    }
+
+    /// Roll a table, returning each item once with everything that dropped of it
+    pub fn roll(&self, table: &str, rng: &mut StdRng) -> Vec<ItemStack> {
+        let mut drops = Vec::new();
+        self.roll_into(table, rng, &mut drops, 0);
+        drops
+    }
+
+    fn roll_into(&self, table: &str, rng: &mut StdRng, drops: &mut Vec<ItemStack>, depth: usize) {
+        let Some(table) = self.tables.get(table).filter(|_| depth < MAX_DEPTH) else {
+            return;
+        };
+        for entry in &table.guaranteed {
+            self.give(entry, rng, drops, depth);
+        }
+
+        let total: u32 = table.entries.iter().map(|e| e.weight).sum();
+        if total == 0 {
+            return;
+        }
+        for _ in 0..rng.random_range(table.rolls.min..=table.rolls.max) {
+            let mut pick = rng.random_range(0..total);
+            let Some(entry) = table.entries.iter().find(|e| {
+                if pick < e.weight {
+                    return true;
+                }
+                pick -= e.weight;
+                false
+            }) else {
+                continue;
+            };
+            self.give(entry, rng, drops, depth);
+        }
+    }
+
+    fn give(&self, entry: &LootEntry, rng: &mut StdRng, drops: &mut Vec<ItemStack>, depth: usize) {
+        if let Some(table) = &entry.table {
+            self.roll_into(table, rng, drops, depth + 1);
+            return;
+        }
+        let Some(item) = &entry.item else {
+            return;
+        };
+        let count = rng.random_range(entry.min..=entry.max);
+        if count == 0 {
+            return;
+        }
+        match drops.iter_mut().find(|s| &s.item == item) {
+            Some(stack) => stack.count += count,
+            None => drops.push(ItemStack::new(item.clone(), count)),
+        }
+    }
+}
+
+/// The random number generator loot is rolled with. `--seed <number>` makes drops repeatable,
+/// replays take their seed from the replay, and otherwise it's different every time.
+pub fn rng_from_args() -> Result<StdRng, String> {
+    let args = std::env::args().collect::<Vec<String>>();
+    if let Some(i) = args.iter().position(|a| a == "--seed") {
+        let Some(seed) = args.get(i + 1).and_then(|s| s.parse::<u64>().ok()) else {
+            return Err("--seed needs a number".to_string());
+        };
+        return Ok(StdRng::seed_from_u64(seed));
+    }
+    let seed = if replay::is_active() {
+        // the replay has already seeded macroquad's generator
+        ((macroquad::rand::rand() as u64) << 32) | macroquad::rand::rand() as u64
+    } else {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default()
+    };
+    Ok(StdRng::seed_from_u64(seed))
+}
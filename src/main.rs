@@ -1,9 +1,9 @@
-use std::time::Instant;
+use std::time::Duration;
 use gamestate::GameState;
 use macroquad::prelude::*;
 use crate::overlay::OverlayManager;
 use crate::startup::startup_loading_screen;
-use crate::world::World;
+use crate::timestep::{FixedTimestep, FIXED_TIMESTEP};
 /***
  # SAVED RESOURCES:
    https://docs.rs/macroquad/latest/macroquad/
@@ -18,29 +18,34 @@ use crate::world::World;
 
 mod controls;
 mod gamestate;
+mod item;
 mod logging;
+mod loot;
 mod world;
 mod ui;
 mod util;
 mod assets;
+mod backend;
+mod camera;
 mod gamedata;
 mod settings;
 mod animation;
 mod overlay;
 mod minigame;
 mod error;
+mod gamepad;
+mod replay;
+mod save;
 mod startup;
+mod timestep;
 
 /***
  * TODO:
  *   - Better error handling + log files
  *   - Plan Story
- *   - Add a scene/world system
  *   - Add a UI system - could use new overlay system or be drawn by the current gamestate
  *   - Particle System
- *   - Fix scaling system (when window is resized rocks dont respawn)
  *   - Fix wall collision system (Player can go off right and down)
- *   - Add a settings menu
 ***/
 
 const FPS_SMOOTHING_FRAMES: usize = 30;
@@ -49,25 +54,41 @@ const DEBUG_OUTPUT: bool = cfg!(debug_assertions);
 
 const BASE_WINDOW_SIZE: (i32, i32) = (1000, 700);
 
-// todo: make it default fullscreen?
-fn window_config() -> Conf {
+fn window_config(settings: &settings::Settings) -> Conf {
     Conf {
         window_title: "Monkey Business".to_string(),
-        window_width: BASE_WINDOW_SIZE.0,
-        window_height: BASE_WINDOW_SIZE.1,
+        window_width: settings.window_size.0,
+        window_height: settings.window_size.1,
+        fullscreen: settings.fullscreen,
+        platform: macroquad::miniquad::conf::Platform {
+            swap_interval: Some(if settings.vsync { 1 } else { 0 }),
+            ..Default::default()
+        },
         ..Default::default()
     }
 }
 
-#[macroquad::main(window_config)]
-async fn main() {
+fn main() {
+    // the window has to be configured before anything else is loaded, so read the settings first
+    // and hand them on to the game rather than reading the file again
+    let settings = settings::Settings::load();
+    macroquad::Window::from_config(window_config(&settings), run(settings));
+}
+
+async fn run(settings: settings::Settings) {
+    game(settings).await;
+    // however the game ended, the frame being recorded still has to be written
+    replay::finish();
+}
+
+async fn game(settings: settings::Settings) {
     // windowed fullscreen cheat
     // let (width, height) = (screen_width(), screen_height());
     // set_fullscreen(false);
     // request_new_screen_size(width, height);
 
     // create a dynamic gamestate object
-    let gamestate = gamestate::playing::PlayingGS::new();
+    let gamestate = gamestate::main_menu::MainMenuGS::new();
     if let Err(e) = gamestate {
         return error!("{}", e);
     }
@@ -76,67 +97,43 @@ async fn main() {
     // create the overlay manager
     let mut overlay_manager = OverlayManager::new();
 
-    let gamedata = startup_loading_screen().await;
+    // a replay has to pick its random seed before the world spawns anything
+    if let Err(e) = replay::start_from_args() {
+        return error!("Failed to start replay: {}", e);
+    }
+
+    let gamedata = startup_loading_screen(settings).await;
     if let Err(e) = gamedata {
         return error!("Failed to load game data: {}", e);
     }
     let mut gamedata = gamedata.unwrap();
 
-    // // create the global assets object
-    // let global_assets = assets::GlobalAssets::load().await;
-    // if let Err(e) = global_assets {
-    //     return error!("Failed to load global assets: {}", e);
-    // }
-    // let global_assets = global_assets.unwrap();
-    //
-    // load the settings
-    // todo: let settings = settings::Settings::load();
-    // let settings = settings::Settings {
-    //     volume: 1.0,
-    //     mute: false,
-    // };
-    //
-    // // load the control handler
-    // let control_handler = controls::ControlHandler::load();
-    // if let Err(e) = control_handler {
-    //     return error!("Failed to load control handler: {}", e);
-    // }
-    // let mut control_handler = control_handler.unwrap();
-    // // TODO: remove this when settings are implemented properly.
-    // //   this changes the bind based on the controls.dat file, which is currently the only way
-    // //   to change sprinting between toggled and held
-    // control_handler.set_sprint_toggle(control_handler.is_sprint_toggle());
-    //
-    // // create the world
-    // let world = World::new(&global_assets).await;
-    // if let Err(e) = world {
-    //     return error!("Failed to create world: {}", e);
-    // }
-    // let world = world.unwrap();
-    //
-    // // create the gamedata object
-    // let mut gamedata = gamedata::GameData {
-    //     fps: 0.0,
-    //     assets: global_assets.clone(),
-    //     settings,
-    //     control_handler,
-    //     world,
-    // };
-
     // handle FPS calculations
-    let mut last_time = Instant::now();
+    // the clock started while loading, so throw that time away instead of simulating it
+    gamedata.backend.frame_time();
     let mut fps_values = vec![0.0; FPS_SMOOTHING_FRAMES];
     let mut fps_index = 0;
     let mut fps_sum = 0.0;
 
+    // splits each frame's time into fixed updates
+    let mut timestep = FixedTimestep::default();
+
+    // closing the window is handled in the loop so the game can autosave first
+    prevent_quit();
+
     // render loop
     loop {
+        if is_quit_requested() {
+            overlay_manager.clear(&mut gamedata);
+            gamestate.quit(&mut gamedata);
+            break;
+        }
+
         // Calculate delta time
-        let now = Instant::now();
-        let delta_time = now - last_time;
-        last_time = now;
-        // Convert delta time to seconds as a float
-        let delta_seconds = delta_time.as_secs_f32();
+        let delta_time = gamedata.backend.frame_time();
+        // Convert delta time to seconds as a float, using the recorded frame time when a replay is playing
+        let delta_seconds = replay::begin_frame(delta_time.as_secs_f32());
+        let delta_time = Duration::from_secs_f32(delta_seconds);
         // Use delta_seconds for movement, animation, etc.
 
         // Calculate the averaged FPS
@@ -157,6 +154,13 @@ async fn main() {
         };
 
         // === UPDATE ===
+        // read controller input before anything checks for actions
+        gamepad::poll();
+        controls::update_held_inputs(delta_seconds, &gamedata.control_handler, gamedata.backend.as_ref());
+        gamedata.notifications.update(delta_seconds);
+        // pick up edits made to controls.dat outside the game, whatever state the game is in
+        gamedata.watch_controls(delta_seconds);
+
         // run the gamestate's persistent update function
         if let Err(e) = gamestate.persistent_update(&delta_time, &mut gamedata) {
             return error!("Failed to run persistent update: {}", e);
@@ -175,6 +179,22 @@ async fn main() {
                             }
                         }
                     }
+                    overlay::OverlayAction::ChangeState(new_state) => {
+                        overlay_manager.clear(&mut gamedata);
+                        gamestate = new_state;
+                        timestep.reset();
+                    }
+                    overlay::OverlayAction::Quit => {
+                        overlay_manager.clear(&mut gamedata);
+                        gamestate.quit(&mut gamedata);
+                        break;
+                    }
+                    overlay::OverlayAction::ExitAll => {
+                        overlay_manager.clear(&mut gamedata);
+                        if let Err(e) = gamestate.restore(&mut gamedata) {
+                            return error!("Failed to restore gamestate: {}", e);
+                        }
+                    }
                     overlay::OverlayAction::SpawnOverlay(overlay) => match overlay_manager.push(overlay, &mut gamedata) {
                         Ok(_) => {}
                         Err(e) => return error!("Failed to push overlay: {}", e),
@@ -185,9 +205,13 @@ async fn main() {
             }
         } else {
             // update the gamestate
+            gamedata.control_handler.set_context(gamestate.input_context());
             match gamestate.update(&delta_time, &mut gamedata) {
                 Ok(action) => match action {
-                    gamestate::GameStateAction::ChangeState(new_state) => gamestate = new_state,
+                    gamestate::GameStateAction::ChangeState(new_state) => {
+                        gamestate = new_state;
+                        timestep.reset();
+                    }
                     gamestate::GameStateAction::SpawnOverlay(overlay) =>{
                         match overlay_manager.push(overlay,&mut gamedata) {
                             Ok(_) => {}
@@ -198,7 +222,22 @@ async fn main() {
                             return error!("Failed to pause gamestate: {}", e);
                         }
                     },
-                    gamestate::GameStateAction::NoOp => {}
+                    gamestate::GameStateAction::Quit => {
+                        gamestate.quit(&mut gamedata);
+                        break;
+                    }
+                    gamestate::GameStateAction::ChangeScene { scene, spawn } => {
+                        gamedata.scenes.travel(scene, spawn);
+                    }
+                    gamestate::GameStateAction::NoOp => {
+                        // simulate in fixed steps, carrying leftover time over to the next frame
+                        for _ in 0..timestep.advance(delta_time) {
+                            if let Err(e) = gamestate.fixed_update(&FIXED_TIMESTEP, &mut gamedata) {
+                                return error!("Failed to run fixed update: {}", e);
+                            }
+                        }
+                        gamedata.interpolation = timestep.interpolation();
+                    }
                 },
                 Err(e) => return error!("Failed to update gamestate: {}", e),
             }
@@ -217,6 +256,9 @@ async fn main() {
             return error!("Failed to draw overlay: {}", e);
         }
 
+        // notifications go over menus too, so things like "Settings saved" can be seen
+        gamedata.notifications.draw(&gamedata.assets, gamedata.settings.ui_scale, gamedata.backend.screen_size());
+
         next_frame().await;
     }
 }
@@ -1,23 +1,40 @@
 use std::time::Duration;
 use macroquad::color::Color;
-use macroquad::input::mouse_position;
-use macroquad::math::{vec2, Rect};
-use macroquad::prelude::{draw_rectangle, screen_height, screen_width};
+use macroquad::math::{vec2, Rect, Vec2};
+use macroquad::prelude::draw_rectangle;
 use macroquad::rand::gen_range;
-use crate::{debug, BASE_WINDOW_SIZE};
-use crate::controls::Action;
+use macroquad::text::measure_text;
+use crate::assets::GlobalAssets;
+use crate::debug;
+use crate::backend::Backend;
+use crate::controls::{Action, InputContext};
 use crate::error::GameError;
 use crate::gamedata::GameData;
+use crate::item::{ToolStats, FISTS};
 use crate::overlay::{Overlay, OverlayAction};
-use crate::util::scale_position;
+use crate::ui::widgets::{BUTTON_FILL, HIGHLIGHT};
+use crate::util::{draw_ansi_text_faded, draw_rounded_rect, remove_ansii_escape_codes};
 use crate::world::rock::Rock;
 use crate::world::interactable::InteractableAttribute;
 
+/// How many times the tool's damage a critical hit does
+const CRIT_MULTIPLIER: u32 = 2;
+// seconds "Critical hit!" stays up
+const CRIT_FLASH: f32 = 0.6;
+
 #[derive(Debug)]
 pub struct MineRock {
     rock_id: u32,
     clicks: u32,
+    // hits it takes to break the rock
+    durability: u32,
     recently_clicked: bool,
+    // what the player is mining with, the inventory can't change while mining
+    tool_name: String,
+    tool: ToolStats,
+    // the tool the rock needs, if the player's isn't good enough
+    required_tool: Option<String>,
+    crit_timer: f32,
 }
 
 impl MineRock {
@@ -25,19 +42,31 @@ impl MineRock {
         Ok(Box::new(Self {
             rock_id,
             clicks: 0,
+            durability: 1,
             recently_clicked: false,
+            tool_name: String::new(),
+            tool: FISTS,
+            required_tool: None,
+            crit_timer: 0.0,
         }))
     }
 
-    fn is_click_inside_rock(&self) -> bool {
-        let mouse_pos = vec2(mouse_position().0, mouse_position().1);
+    /// Draw a line of ANSI colored text centered across the screen, `y` being its baseline
+    fn draw_centered(text: &str, y: f32, font_size: u16, alpha: f32, screen: Vec2, assets: &GlobalAssets) {
+        let size = measure_text(&remove_ansii_escape_codes(text), Some(&assets.font), font_size, 1.0);
+        draw_ansi_text_faded(text, vec2((screen.x - size.width) / 2.0, y), assets, font_size, 0.0, alpha);
+    }
+
+    fn is_click_inside_rock(&self, backend: &dyn Backend) -> bool {
+        let mouse_pos = backend.mouse_position();
+        let screen = backend.screen_size();
         let rock_scale = if self.recently_clicked {
             vec2(300.0, 300.0)
         } else {
             vec2(256.0, 256.0)
         };
-        let rock_pos = vec2(screen_width() / 2.0 - rock_scale.x / 2.0,
-                            screen_height() / 2.0 - rock_scale.y / 2.0);
+        let rock_pos = vec2(screen.x / 2.0 - rock_scale.x / 2.0,
+                            screen.y / 2.0 - rock_scale.y / 2.0);
         let rock_rect = Rect::new(rock_pos.x, rock_pos.y, rock_scale.x, rock_scale.y);
         rock_rect.contains(mouse_pos)
     }
@@ -55,28 +84,53 @@ impl Overlay for MineRock {
         } else {
             0
         };
+        self.durability = match rock.get_attribute("durability") {
+            Some(InteractableAttribute::UInt(durability)) => durability.max(1),
+            _ => return Err(GameError::Initialization(format!("Rock with id {} has no durability", self.rock_id))),
+        };
+
+        let tier = match rock.get_attribute("kind") {
+            Some(InteractableAttribute::String(kind)) => data.assets.rocks.get(&kind).map(|r| r.tool_tier).unwrap_or(0),
+            _ => 0,
+        };
+        (self.tool_name, self.tool) = data.world.player.tool_stats(&data.items);
+        self.required_tool = if self.tool.tier < tier {
+            Some(data.items.tool_for_tier(tier)
+                .map(|def| def.display_name())
+                .unwrap_or_else(|| format!("a tier {} tool", tier)))
+        } else {
+            None
+        };
 
         Ok(())
     }
 
-    fn update(&mut self, _delta_time: &Duration, data: &mut GameData) -> Result<OverlayAction, GameError> {
+    fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<OverlayAction, GameError> {
         self.recently_clicked = false;
-        // handle on release to ensure pause key isn't spammed when held (was an issue)
-        let actions = data.control_handler.get_actions();
+        self.crit_timer = (self.crit_timer - delta_time.as_secs_f32()).max(0.0);
+        // pause is bound as a press capture, so holding the key won't reopen the menu every frame
+        let actions = data.control_handler.get_actions(data.backend.as_ref());
         for action in actions {
             match action {
-                Action::Pause => {
+                Action::Pause | Action::UIBack => {
                     return Ok(OverlayAction::Exit);
                 }
                 Action::BasicAttack => {
-                    if self.is_click_inside_rock() {
+                    if self.is_click_inside_rock(data.backend.as_ref()) {
                         self.recently_clicked = true;
                     }
                 }
-                Action::UIClick => {
-                    if self.is_click_inside_rock() {
-                        // increment clicks
-                        self.clicks += 1;
+                Action::UIClick | Action::UIConfirm => {
+                    // confirming on a gamepad always hits the rock since there is no cursor
+                    if action == Action::UIConfirm || self.is_click_inside_rock(data.backend.as_ref()) {
+                        if self.required_tool.is_some() {
+                            // the tool just bounces off
+                            data.camera.shake(0.05);
+                            continue;
+                        }
+                        let crit = self.tool.crit_chance > 0.0 && gen_range(0.0, 1.0) < self.tool.crit_chance;
+                        let damage = if crit { self.tool.damage * CRIT_MULTIPLIER } else { self.tool.damage };
+                        self.clicks = (self.clicks + damage).min(self.durability);
                         // write the clicks to the rock
                         let Some(rock) = data.world.get_mut_interactable_by_id(self.rock_id) else {
                             return Err(GameError::Update(format!("Rock with id {} not found", self.rock_id)));
@@ -84,20 +138,39 @@ impl Overlay for MineRock {
                         if let Err(e) = rock.set_attribute("clicks", InteractableAttribute::UInt(self.clicks)) {
                             return Err(GameError::Update(format!("Failed to set clicks: {}", e)));
                         }
-                        if self.clicks >= 16 {
+                        if crit {
+                            self.crit_timer = CRIT_FLASH;
+                            data.camera.shake(0.25);
+                        } else {
+                            data.camera.shake(0.1);
+                        }
+                        if self.clicks >= self.durability {
+                            data.camera.shake(0.4);
+                            let name = rock.get_name();
+                            let kind = match rock.get_attribute("kind") {
+                                Some(InteractableAttribute::String(kind)) => kind,
+                                _ => String::new(),
+                            };
                             if let Err(e) = data.world.break_interactable(self.rock_id) {
                                 return Err(GameError::Update(format!("Failed to break rock: {}", e)));
                             }
 
                             // add a new rock to the world
-                            let pos = scale_position(vec2(gen_range(0.0, BASE_WINDOW_SIZE.0 as f32), gen_range(0.0, BASE_WINDOW_SIZE.1 as f32)));
-                            debug!("Rock at {}", pos); // todo this is broken if the window is not default size
+                            let pos = data.world.random_position();
+                            debug!("Rock at {}", pos);
+                            let new_kind = data.assets.rocks.random().id.clone();
                             let new_rock = Rock::new(&data.assets, self.rock_id,
-                                                     "Rock Pile".to_string(),
-                                                     scale_position(vec2(gen_range(0.0, BASE_WINDOW_SIZE.0 as f32), gen_range(0.0, BASE_WINDOW_SIZE.1 as f32))),
+                                                     &new_kind,
+                                                     None,
+                                                     pos,
                                                      gen_range(0.0, 360.0));
 
                             data.world.add_interactable(Box::new(new_rock));
+                            // rocks without a table of their own drop whatever rocks drop
+                            let table = data.assets.rocks.get(&kind)
+                                .and_then(|r| r.loot.clone())
+                                .or_else(|| data.loot.table_for("rock").map(|t| t.to_string()));
+                            data.drop_loot(table.as_deref(), format!("{}{} broken!", better_term::Color::BrightYellow, name));
 
                             return Ok(OverlayAction::Exit);
                         }
@@ -122,7 +195,8 @@ impl Overlay for MineRock {
         };
 
         // draw a semi-transparent overlay
-        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::from_rgba(61,51,51,150));
+        let screen = data.backend.screen_size();
+        draw_rectangle(0.0, 0.0, screen.x, screen.y, Color::from_rgba(61,51,51,150));
 
         let scale = if self.recently_clicked {
             vec2(300.0, 300.0)
@@ -136,9 +210,36 @@ impl Overlay for MineRock {
         };
 
         // draw the rock at the center of the screen
-        rock_sprite.draw(vec2(screen_width() / 2.0 - scale.x / 2.0,
-                              screen_height() / 2.0 - scale.y / 2.0),
-        Some(rock.get_rotation()), Some(scale));
+        rock_sprite.draw(vec2(screen.x / 2.0 - scale.x / 2.0,
+                              screen.y / 2.0 - scale.y / 2.0),
+        Some(rock.get_rotation()), scale);
+
+        // the tool and how close the rock is to breaking, under the rock
+        let ui_scale = data.settings.ui_scale;
+        let font_size = (12.0 * ui_scale).round() as u16;
+        let bar_size = vec2(256.0, 10.0 * ui_scale);
+        let bar_pos = vec2((screen.x - bar_size.x) / 2.0, screen.y / 2.0 + 150.0 + 8.0 * ui_scale);
+        draw_rounded_rect(bar_pos, bar_size, 2.0, BUTTON_FILL, true, None);
+        let progress = self.clicks.min(self.durability) as f32 / self.durability as f32;
+        if progress > 0.0 {
+            draw_rounded_rect(bar_pos, vec2(bar_size.x * progress, bar_size.y), 2.0, HIGHLIGHT, false, None);
+        }
+        let mut y = bar_pos.y + bar_size.y + font_size as f32 + 8.0 * ui_scale;
+        Self::draw_centered(&format!("{}{} - damage {}{}{}, crit {}{}%", self.tool_name, better_term::Color::White,
+                                     better_term::Color::BrightYellow, self.tool.damage, better_term::Color::White,
+                                     better_term::Color::BrightYellow, (self.tool.crit_chance * 100.0).round()),
+                            y, font_size, 1.0, screen, &data.assets);
+        if let Some(required) = &self.required_tool {
+            y += font_size as f32 + 6.0 * ui_scale;
+            Self::draw_centered(&format!("{}Requires {}", better_term::Color::BrightRed, required),
+                                y, font_size, 1.0, screen, &data.assets);
+        }
+
+        if self.crit_timer > 0.0 {
+            Self::draw_centered(&format!("{}Critical hit!", better_term::Color::BrightYellow),
+                                screen.y / 2.0 - 160.0, (16.0 * ui_scale).round() as u16,
+                                self.crit_timer / CRIT_FLASH, screen, &data.assets);
+        }
 
         // draw large text at the bottom of the screen displaying the number of clicks
         // let text = format!("Clicks: {}", self.clicks);
@@ -159,4 +260,8 @@ impl Overlay for MineRock {
     fn draw_below(&self) -> bool {
         true
     }
+
+    fn input_context(&self) -> InputContext {
+        InputContext::Minigame
+    }
 }
\ No newline at end of file
@@ -0,0 +1,391 @@
+use std::time::Duration;
+
+use macroquad::color::{Color, WHITE};
+use macroquad::input::{KeyCode, MouseButton};
+use macroquad::math::{vec2, Vec2};
+use macroquad::shapes::draw_rectangle;
+use crate::controls::{u16_to_keycode, u16_to_mousecode, Action, Binding, BindingType, ExpectedPressType};
+use crate::error::GameError;
+use crate::gamedata::GameData;
+use crate::gamepad::{self, GamepadButton, GamepadInput};
+use crate::overlay::{Overlay, OverlayAction};
+use crate::replay;
+use crate::ui::widgets::{Ui, UiInput, UiState, HIGHLIGHT};
+
+const PANEL_WIDTH: f32 = 600.0;
+// a widget row and the space under it, and the panel's padding and title around the rows
+const ROW_PITCH: f32 = 32.0;
+const PANEL_CHROME: f32 = 48.0;
+
+/// the longest chord that can be captured
+const MAX_CHORD_LENGTH: usize = 3;
+/// holding the back input on its own this long cancels a capture, tapping it binds it
+const CANCEL_HOLD: f32 = 1.0;
+/// a capture with nothing pressed for this long is cancelled
+const CAPTURE_TIMEOUT: f32 = 5.0;
+/// the hold column cycles through these, in milliseconds, `None` fires without holding
+const HOLD_TIMES: [Option<u32>; 4] = [None, Some(250), Some(500), Some(1000)];
+
+const MOUSE_BUTTONS: [MouseButton; 3] = [MouseButton::Left, MouseButton::Middle, MouseButton::Right];
+
+const WARNING_COLOR: Color = Color::new(1.0, 0.33, 0.33, 1.0);
+const UNBOUND_COLOR: Color = Color::new(170.0 / 255.0, 170.0 / 255.0, 170.0 / 255.0, 1.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlsButton {
+    ResetAll,
+    Done,
+}
+
+const BUTTONS: [ControlsButton; 2] = [ControlsButton::ResetAll, ControlsButton::Done];
+
+impl ControlsButton {
+    fn label(&self) -> &'static str {
+        match self {
+            ControlsButton::ResetAll => "Reset All",
+            ControlsButton::Done => "Done",
+        }
+    }
+}
+
+/// The clickable parts of each action's row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowColumn {
+    Keyboard,
+    Gamepad,
+    Hold,
+    Reset,
+}
+
+const COLUMNS: [RowColumn; 4] = [RowColumn::Keyboard, RowColumn::Gamepad, RowColumn::Hold, RowColumn::Reset];
+
+impl RowColumn {
+    fn header(&self) -> &'static str {
+        match self {
+            RowColumn::Keyboard => "Keyboard",
+            RowColumn::Gamepad => "Gamepad",
+            RowColumn::Hold => "Hold",
+            RowColumn::Reset => "",
+        }
+    }
+}
+
+/// The action currently waiting for a new binding, and every input pressed since capture started
+#[derive(Debug)]
+struct Capture {
+    action: Action,
+    gamepad: bool,
+    inputs: Vec<BindingType>,
+    // seconds with nothing pressed, and seconds the back input has been held on its own
+    idle: f32,
+    cancel_held: f32,
+}
+
+/// Lists every action and lets the player rebind it by clicking the binding and pressing the new
+/// key, mouse button, gamepad input or chord. The chord is finished as soon as any of its inputs is released.
+/// A capture is cancelled by holding Esc (or the gamepad's back button when rebinding the gamepad),
+/// pressing the other device's back button, or pressing nothing for a few seconds.
+/// Rebinding is turned off while a replay records or plays, since replays only keep the actions
+/// that fired and not the raw keys and buttons a capture reads.
+#[derive(Debug)]
+pub struct ControlsOverlay {
+    capture: Option<Capture>,
+    status: Option<(String, Color)>,
+    ui: UiState,
+}
+
+impl ControlsOverlay {
+    pub fn new() -> Self {
+        Self {
+            capture: None,
+            status: None,
+            ui: UiState::default(),
+        }
+    }
+
+    /// Show what changed, or why it couldn't be saved
+    fn saved(&mut self, result: Result<(), String>, message: String, color: Color) {
+        self.status = match result {
+            Ok(_) => Some((message, color)),
+            Err(e) => Some((format!("Couldn't save controls: {}", e), WARNING_COLOR)),
+        };
+    }
+
+    /// the panel shrinks the ui scale if needed so every action fits on screen
+    fn effective_scale(ui_scale: f32, screen: Vec2) -> f32 {
+        ui_scale.min(screen.y / Self::panel_size().y)
+    }
+
+    fn panel_size() -> Vec2 {
+        // the headers, every action, the status line and the buttons
+        vec2(PANEL_WIDTH, PANEL_CHROME + ROW_PITCH * (Action::ALL.len() as f32 + 3.0))
+    }
+
+    /// what a binding button shows, and in which color
+    fn binding_text(capture: Option<&Capture>, action: &Action, column: RowColumn, data: &GameData) -> (String, Color) {
+        let binding = match column {
+            RowColumn::Keyboard => data.control_handler.get_binding(action),
+            RowColumn::Gamepad => data.control_handler.get_gamepad_binding(action),
+            RowColumn::Hold => {
+                let text = match data.control_handler.hold_time(action) {
+                    Some(ms) => format!("{}s", ms as f32 / 1000.0),
+                    None => "Tap".to_string(),
+                };
+                return (text, WHITE);
+            }
+            RowColumn::Reset => return ("Reset".to_string(), WHITE),
+        };
+
+        let capturing = capture.is_some_and(|c| c.action == *action && c.gamepad == (column == RowColumn::Gamepad));
+        if capturing {
+            ("...".to_string(), HIGHLIGHT)
+        } else if let Some(binding) = binding {
+            let color = if data.control_handler.find_conflicts(action, &binding).is_empty() {
+                WHITE
+            } else {
+                WARNING_COLOR
+            };
+            (binding.to_string(), color)
+        } else {
+            ("Unbound".to_string(), UNBOUND_COLOR)
+        }
+    }
+
+    fn conflict_message(action: &Action, conflicts: &[Action]) -> String {
+        let names = conflicts.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+        format!("{} shares its binding with {}", action, names.join(", "))
+    }
+
+    /// the gamepad input that backs out of menus, used to cancel captures
+    fn gamepad_back(data: &GameData) -> GamepadInput {
+        let binding = data.control_handler.get_gamepad_binding(&Action::UIBack);
+        match binding.as_ref().and_then(|b| b.binding.last()) {
+            Some((BindingType::Gamepad(input), _)) => *input,
+            _ => GamepadInput::Button(GamepadButton::East),
+        }
+    }
+
+    /// Collect new inputs for the capture, finishing it when an input is released.
+    /// Returns true while the capture should keep swallowing input.
+    fn update_capture(&mut self, delta_seconds: f32, data: &mut GameData) -> bool {
+        let gamepad_back = Self::gamepad_back(data);
+        let Some(capture) = &mut self.capture else {
+            return false;
+        };
+
+        let backend = data.backend.as_ref();
+        let handler = &data.control_handler;
+        // the back input of the device being captured can be bound with a tap, so only holding it cancels
+        let own_back = if capture.gamepad { BindingType::Gamepad(gamepad_back) } else { BindingType::Key(KeyCode::Escape as u16) };
+        let other_back_pressed = if capture.gamepad {
+            backend.is_key_pressed(KeyCode::Escape)
+        } else {
+            gamepad::is_input_pressed(backend, &gamepad_back, handler.deadzone_for(&gamepad_back))
+        };
+        if capture.inputs == [own_back] {
+            capture.cancel_held += delta_seconds;
+        }
+        if capture.inputs.is_empty() {
+            capture.idle += delta_seconds;
+        }
+        if other_back_pressed || capture.cancel_held >= CANCEL_HOLD || capture.idle >= CAPTURE_TIMEOUT {
+            self.capture = None;
+            self.status = Some(("Rebinding cancelled".to_string(), WHITE));
+            return true;
+        }
+
+        let mut pressed = Vec::new();
+        if capture.gamepad {
+            pressed.extend(gamepad::get_inputs_pressed(backend, handler.stick_deadzone(), handler.trigger_deadzone())
+                .into_iter()
+                .map(BindingType::Gamepad));
+        } else {
+            // keys that can't be saved to controls.dat are ignored rather than bound
+            pressed.extend(backend.keys_pressed().into_iter().filter_map(BindingType::from_key));
+            pressed.extend(MOUSE_BUTTONS.iter()
+                .filter(|button| backend.is_mouse_button_pressed(**button))
+                .map(|button| BindingType::Mouse(*button as u16)));
+        }
+        for bind in pressed {
+            if !capture.inputs.contains(&bind) && capture.inputs.len() < MAX_CHORD_LENGTH {
+                capture.inputs.push(bind);
+            }
+        }
+
+        let released = capture.inputs.iter().any(|bind| match bind {
+            BindingType::Key(k) => backend.is_key_released(u16_to_keycode(*k)),
+            BindingType::Mouse(m) => backend.is_mouse_button_released(u16_to_mousecode(*m)),
+            BindingType::Gamepad(g) => gamepad::is_input_released(backend, g, handler.deadzone_for(g)),
+        });
+        if !released {
+            return true;
+        }
+
+        let Some(capture) = self.capture.take() else {
+            return true;
+        };
+
+        // the last input keeps how the action was triggered before (hold, release, ...), the rest are modifiers
+        let previous = if capture.gamepad {
+            data.control_handler.get_gamepad_binding(&capture.action)
+        } else {
+            data.control_handler.get_binding(&capture.action)
+        };
+        let last_press_type = previous
+            .and_then(|b| b.binding.last().map(|(_, ept)| ept.clone()))
+            .unwrap_or(ExpectedPressType::Press);
+        let count = capture.inputs.len();
+        let binding = Binding::new(capture.inputs.into_iter().enumerate().map(|(i, bind)| {
+            if i + 1 == count {
+                (bind, last_press_type.clone())
+            } else {
+                (bind, ExpectedPressType::Press)
+            }
+        }).collect());
+
+        let conflicts = data.control_handler.find_conflicts(&capture.action, &binding);
+        let (message, color) = if conflicts.is_empty() {
+            (format!("{} bound to {}", capture.action, binding), WHITE)
+        } else {
+            (Self::conflict_message(&capture.action, &conflicts), WARNING_COLOR)
+        };
+        let result = if capture.gamepad {
+            data.control_handler.edit_gamepad_binding(capture.action, binding)
+        } else {
+            data.control_handler.edit_keybind(capture.action, binding)
+        };
+        self.saved(result, message, color);
+
+        true
+    }
+
+    fn activate_row(&mut self, action: &Action, column: RowColumn, data: &mut GameData) {
+        match column {
+            RowColumn::Keyboard | RowColumn::Gamepad => {
+                let gamepad = column == RowColumn::Gamepad;
+                self.capture = Some(Capture {
+                    action: action.clone(),
+                    gamepad,
+                    inputs: Vec::new(),
+                    idle: 0.0,
+                    cancel_held: 0.0,
+                });
+                let (device, back) = if gamepad {
+                    ("gamepad input", String::from(BindingType::Gamepad(Self::gamepad_back(data))))
+                } else {
+                    ("key or mouse button", "Esc".to_string())
+                };
+                let message = format!("Press the new {} for {} (hold {} to cancel)", device, action, back);
+                self.status = Some((message, HIGHLIGHT));
+            }
+            RowColumn::Hold => {
+                let current = data.control_handler.hold_time(action);
+                let index = HOLD_TIMES.iter().position(|t| *t == current).unwrap_or(0);
+                let next = HOLD_TIMES[(index + 1) % HOLD_TIMES.len()];
+                let result = data.control_handler.set_hold_time(action, next);
+                let message = match next {
+                    Some(ms) => format!("{} fires after holding for {}s", action, ms as f32 / 1000.0),
+                    None => format!("{} fires without holding", action),
+                };
+                self.saved(result, message, WHITE);
+            }
+            RowColumn::Reset => {
+                let result = data.control_handler.reset_binding(action);
+                self.saved(result, format!("{} reset to default", action), WHITE);
+            }
+        }
+    }
+
+    fn activate_button(&mut self, button: ControlsButton, data: &mut GameData) -> OverlayAction {
+        match button {
+            ControlsButton::ResetAll => {
+                let result = data.control_handler.reset_all();
+                self.saved(result, "All controls reset to default".to_string(), WHITE);
+                OverlayAction::NoOp
+            }
+            ControlsButton::Done => OverlayAction::Exit,
+        }
+    }
+}
+
+impl Overlay for ControlsOverlay {
+
+    fn init(&mut self, _data: &mut GameData) -> Result<(), GameError> {
+        Ok(())
+    }
+
+    fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<OverlayAction, GameError> {
+        // the panel is still built while capturing so it keeps drawing, it just gets no actions
+        let actions = if self.update_capture(delta_time.as_secs_f32(), data) {
+            Vec::new()
+        } else {
+            data.control_handler.get_actions(data.backend.as_ref())
+        };
+
+        let ui_scale = Self::effective_scale(data.settings.ui_scale, data.backend.screen_size());
+        let input = UiInput::new(actions, data);
+        let mut ui = Ui::begin(&mut self.ui, input, ui_scale, delta_time.as_secs_f32());
+        ui.panel(Self::panel_size(), "Controls");
+
+        ui.columns(COLUMNS.len() + 1);
+        ui.label("");
+        for column in COLUMNS {
+            ui.label_colored(column.header(), HIGHLIGHT);
+        }
+        let replaying = replay::is_active();
+        let mut pressed_row = None;
+        for action in Action::ALL.iter() {
+            ui.columns(COLUMNS.len() + 1);
+            ui.label(&action.to_string());
+            for column in COLUMNS {
+                let (text, color) = Self::binding_text(self.capture.as_ref(), action, column, data);
+                let pressed = match column {
+                    RowColumn::Keyboard | RowColumn::Gamepad if replaying => ui.button_enabled(&text, false),
+                    _ => ui.button_colored(&text, color),
+                };
+                if pressed {
+                    pressed_row = Some((action.clone(), column));
+                }
+            }
+        }
+
+        let (status, color) = match &self.status {
+            Some(status) => status.clone(),
+            None if replaying => ("Rebinding is off while a replay is recording or playing".to_string(), WARNING_COLOR),
+            None => Default::default(),
+        };
+        ui.label_colored(&status, color);
+        ui.columns(BUTTONS.len());
+        let mut pressed = None;
+        for button in BUTTONS {
+            if ui.button(button.label()) {
+                pressed = Some(button);
+            }
+        }
+        let leftover = ui.end();
+
+        if let Some((action, column)) = pressed_row {
+            self.activate_row(&action, column, data);
+            return Ok(OverlayAction::NoOp);
+        }
+        if let Some(button) = pressed {
+            return Ok(self.activate_button(button, data));
+        }
+        if leftover.iter().any(|a| matches!(a, Action::Pause | Action::UIBack)) {
+            return Ok(OverlayAction::Exit);
+        }
+        Ok(OverlayAction::NoOp)
+    }
+
+    fn draw(&self, data: &mut GameData) -> Result<(), GameError> {
+        let screen = data.backend.screen_size();
+        draw_rectangle(0.0, 0.0, screen.x, screen.y, Color::new(0.0, 0.0, 0.0, 0.5));
+        self.ui.draw(&data.assets);
+        Ok(())
+    }
+
+    fn draw_below(&self) -> bool {
+        true
+    }
+
+}
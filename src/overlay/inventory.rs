@@ -0,0 +1,362 @@
+use std::time::Duration;
+
+use macroquad::color::{Color, WHITE};
+use macroquad::input::MouseButton;
+use macroquad::math::{vec2, Rect, Vec2};
+use macroquad::shapes::draw_rectangle;
+use macroquad::text::{draw_text_ex, measure_text, TextParams};
+use crate::assets::GlobalAssets;
+use crate::controls::{Action, InputContext};
+use crate::error::GameError;
+use crate::gamedata::GameData;
+use crate::item::ItemRegistry;
+use crate::overlay::{Overlay, OverlayAction};
+use crate::ui::popup::{PopupCard, PopupLocation};
+use crate::ui::widgets::{BUTTON_FILL, HIGHLIGHT};
+use crate::ui::tooltip::{tooltip, ToolTipCard};
+use crate::util::draw_rounded_rect;
+use crate::warn;
+use crate::world::inventory::{Inventory, ItemStack};
+
+const COLUMNS: usize = 6;
+const SLOT_SIZE: f32 = 36.0;
+const SLOT_GAP: f32 = 4.0;
+const PADDING: f32 = 12.0;
+const TITLE_HEIGHT: f32 = 28.0;
+const FONT_SIZE: f32 = 10.0;
+
+/// The player's inventory as a grid. Clicking a slot picks its stack up onto the cursor and clicking
+/// another drops it there, merging with the same item or swapping with a different one.
+/// Stacks can also be dragged from one slot and released over another.
+/// Right click splits a stack in half, or drops one item from the held stack. Right clicking a tool equips it.
+#[derive(Debug)]
+pub struct InventoryOverlay {
+    // the stack on the cursor and the slot it came from, so it can go back when the inventory closes
+    held: Option<(ItemStack, usize)>,
+    // the slot picked with the keyboard / gamepad
+    cursor: usize,
+    // the slot the mouse was pressed on with nothing held, which starts a drag if the mouse leaves it
+    pressed_on: Option<usize>,
+    // the held stack was dragged out of its slot and drops when the mouse is released
+    dragging: bool,
+}
+
+impl InventoryOverlay {
+    pub fn new() -> Self {
+        Self {
+            held: None,
+            cursor: 0,
+            pressed_on: None,
+            dragging: false,
+        }
+    }
+
+    fn rows(slots: usize) -> usize {
+        slots.div_ceil(COLUMNS)
+    }
+
+    fn panel_rect(slots: usize, ui_scale: f32, screen: Vec2) -> Rect {
+        let grid = |count: usize| SLOT_SIZE * count as f32 + SLOT_GAP * (count as f32 - 1.0).max(0.0);
+        let size = vec2(grid(COLUMNS) + PADDING * 2.0, grid(Self::rows(slots)) + TITLE_HEIGHT + PADDING * 2.0) * ui_scale;
+        Rect::new((screen.x - size.x) / 2.0, (screen.y - size.y) / 2.0, size.x, size.y)
+    }
+
+    fn slot_rect(index: usize, slots: usize, ui_scale: f32, screen: Vec2) -> Rect {
+        let panel = Self::panel_rect(slots, ui_scale, screen);
+        let (column, row) = ((index % COLUMNS) as f32, (index / COLUMNS) as f32);
+        let step = (SLOT_SIZE + SLOT_GAP) * ui_scale;
+        Rect::new(panel.x + PADDING * ui_scale + step * column,
+                  panel.y + (PADDING + TITLE_HEIGHT) * ui_scale + step * row,
+                  SLOT_SIZE * ui_scale,
+                  SLOT_SIZE * ui_scale)
+    }
+
+    fn slot_at(mouse: Vec2, slots: usize, ui_scale: f32, screen: Vec2) -> Option<usize> {
+        (0..slots).find(|i| Self::slot_rect(*i, slots, ui_scale, screen).contains(mouse))
+    }
+
+    // click or confirm on a slot
+    fn pick_up_or_drop(&mut self, slot: usize, inventory: &mut Inventory, items: &ItemRegistry) {
+        match self.held.take() {
+            None => self.held = inventory.take(slot).map(|stack| (stack, slot)),
+            Some((stack, _)) => {
+                let max_stack = items.max_stack(&stack.item);
+                // anything swapped out now belongs to this slot
+                self.held = inventory.place(slot, stack, max_stack).map(|rest| (rest, slot));
+            }
+        }
+    }
+
+    // releasing a dragged stack, over a slot or anywhere else to put it back
+    fn drop_dragged(&mut self, slot: Option<usize>, inventory: &mut Inventory, items: &ItemRegistry) {
+        let Some(slot) = slot else {
+            self.put_back_held(inventory, items);
+            return;
+        };
+        let Some((stack, from)) = self.held.take() else {
+            return;
+        };
+        self.cursor = slot;
+        let max_stack = items.max_stack(&stack.item);
+        // anything swapped out goes to the slot the dragged stack came from
+        self.held = inventory.place(slot, stack, max_stack).map(|rest| (rest, from));
+        self.put_back_held(inventory, items);
+    }
+
+    // right click on a slot
+    fn split_or_drop_one(&mut self, slot: usize, inventory: &mut Inventory, items: &ItemRegistry) {
+        let Some((mut stack, from)) = self.held.take() else {
+            self.held = inventory.split(slot).map(|half| (half, slot));
+            return;
+        };
+        let max_stack = items.max_stack(&stack.item);
+        let fits = inventory.get(slot).is_none_or(|s| s.item == stack.item && s.count < max_stack);
+        if fits {
+            inventory.place(slot, ItemStack::new(stack.item.clone(), 1), max_stack);
+            stack.count -= 1;
+        }
+        self.held = Some((stack, from)).filter(|(s, _)| s.count > 0);
+    }
+
+    // right click on a tool, equipping it or putting it away if it already is
+    fn toggle_equipped(slot: usize, inventory: &Inventory, tool: &mut Option<String>, items: &ItemRegistry) -> bool {
+        let Some(stack) = inventory.get(slot).filter(|s| items.get(&s.item).is_some_and(|d| d.tool.is_some())) else {
+            return false;
+        };
+        if tool.as_deref() == Some(stack.item.as_str()) {
+            *tool = None;
+        } else {
+            *tool = Some(stack.item.clone());
+        }
+        true
+    }
+
+    /// Put the held stack back wherever it fits. Whatever doesn't fit stays held.
+    /// Returns true if nothing is held anymore.
+    fn put_back_held(&mut self, inventory: &mut Inventory, items: &ItemRegistry) -> bool {
+        let Some((stack, from)) = self.held.take() else {
+            return true;
+        };
+        let max_stack = items.max_stack(&stack.item);
+        let Some(rest) = inventory.place(from, stack, max_stack) else {
+            return true;
+        };
+        // whatever was swapped out of the original slot has its own stack size
+        let left = inventory.add(&rest.item, rest.count, items.max_stack(&rest.item));
+        self.held = Some((ItemStack::new(rest.item, left), from)).filter(|(s, _)| s.count > 0);
+        self.held.is_none()
+    }
+
+    fn move_cursor(&mut self, action: &Action, slots: usize) {
+        if slots == 0 {
+            return;
+        }
+        let rows = Self::rows(slots);
+        let (mut column, mut row) = (self.cursor % COLUMNS, self.cursor / COLUMNS);
+        match action {
+            Action::UIUp => row = (row + rows - 1) % rows,
+            Action::UIDown => row = (row + 1) % rows,
+            Action::UILeft => column = (column + COLUMNS - 1) % COLUMNS,
+            Action::UIRight => column = (column + 1) % COLUMNS,
+            _ => {}
+        }
+        self.cursor = (row * COLUMNS + column).min(slots - 1);
+    }
+
+    fn draw_stack(stack: &ItemStack, rect: Rect, ui_scale: f32, items: &ItemRegistry, assets: &GlobalAssets) {
+        let inner = Rect::new(rect.x + 4.0 * ui_scale, rect.y + 4.0 * ui_scale, rect.w - 8.0 * ui_scale, rect.h - 8.0 * ui_scale);
+        match items.get(&stack.item) {
+            Some(def) => def.draw(inner, assets),
+            None => {
+                // items missing from the registry still show up so they can be moved around
+                draw_rounded_rect(inner.point(), inner.size(), 2.0, Color::from_rgba(0, 0, 0, 80), false, None);
+                Self::draw_text("?", inner.center(), true, ui_scale, assets);
+            }
+        }
+        if stack.count > 1 {
+            Self::draw_text(&stack.count.to_string(), vec2(rect.x + rect.w - 3.0 * ui_scale, rect.y + rect.h - 3.0 * ui_scale), false, ui_scale, assets);
+        }
+    }
+
+    /// centered on `pos`, or with its bottom right corner at `pos`
+    fn draw_text(text: &str, pos: Vec2, centered: bool, ui_scale: f32, assets: &GlobalAssets) {
+        let font_size = (FONT_SIZE * ui_scale).round() as u16;
+        let size = measure_text(text, Some(&assets.font), font_size, 1.0);
+        let (x, y) = if centered {
+            (pos.x - size.width / 2.0, pos.y + size.height / 2.0)
+        } else {
+            (pos.x - size.width, pos.y)
+        };
+        draw_text_ex(text, x, y, TextParams {
+            font: Some(&assets.font),
+            font_size,
+            color: WHITE,
+            ..Default::default()
+        });
+    }
+}
+
+impl Overlay for InventoryOverlay {
+
+    fn init(&mut self, _data: &mut GameData) -> Result<(), GameError> {
+        Ok(())
+    }
+
+    fn update(&mut self, _delta_time: &Duration, data: &mut GameData) -> Result<OverlayAction, GameError> {
+        let actions = data.control_handler.get_actions(data.backend.as_ref());
+        let ui_scale = data.settings.ui_scale;
+        let screen = data.backend.screen_size();
+        let mouse = data.backend.mouse_position();
+        let backend = data.backend.as_ref();
+        let player = &mut data.world.player;
+        let inventory = &mut player.inventory;
+        let items = &data.items;
+        let slots = inventory.slots().len();
+        let hovered = Self::slot_at(mouse, slots, ui_scale, screen);
+
+        if self.held.is_none() && backend.is_mouse_button_pressed(MouseButton::Left) {
+            self.pressed_on = hovered;
+        }
+        if let Some(from) = self.pressed_on {
+            if !backend.is_mouse_button_down(MouseButton::Left) {
+                self.pressed_on = None;
+            } else if hovered != Some(from) {
+                self.pressed_on = None;
+                self.held = inventory.take(from).map(|stack| (stack, from));
+                self.dragging = self.held.is_some();
+            }
+        }
+        // the release that ends a drag is also a click, which shouldn't pick the stack straight back up
+        let dropped = self.dragging && !backend.is_mouse_button_down(MouseButton::Left);
+        if dropped {
+            self.dragging = false;
+            self.drop_dragged(hovered, inventory, items);
+        }
+
+        for action in actions {
+            match action {
+                Action::Inventory | Action::Pause | Action::UIBack => {
+                    if self.put_back_held(inventory, items) {
+                        return Ok(OverlayAction::Exit);
+                    }
+                    // closing now would lose the held stack, so it stays open until it's placed
+                    if let Some((stack, _)) = &self.held {
+                        let lines = vec![format!("Place the {} somewhere first", items.display_name(&stack.item))];
+                        data.notifications.push(PopupCard::new("No room to put it back".to_string(), lines, PopupLocation::Top));
+                    }
+                }
+                Action::UIClick if !dropped => {
+                    if let Some(slot) = hovered {
+                        self.cursor = slot;
+                        self.pick_up_or_drop(slot, inventory, items);
+                    }
+                }
+                Action::UIRightClick if !self.dragging => {
+                    if let Some(slot) = hovered {
+                        self.cursor = slot;
+                        if self.held.is_some() || !Self::toggle_equipped(slot, inventory, &mut player.tool, items) {
+                            self.split_or_drop_one(slot, inventory, items);
+                        }
+                    }
+                }
+                Action::UIConfirm if !self.dragging => self.pick_up_or_drop(self.cursor, inventory, items),
+                Action::UIUp | Action::UIDown | Action::UILeft | Action::UIRight => self.move_cursor(&action, slots),
+                _ => {}
+            }
+        }
+        Ok(OverlayAction::NoOp)
+    }
+
+    fn close(&mut self, data: &mut GameData) {
+        self.dragging = false;
+        let inventory = &mut data.world.player.inventory;
+        if self.put_back_held(inventory, &data.items) {
+            return;
+        }
+        // this close can't be refused, so make room rather than lose the items
+        if let Some((stack, _)) = self.held.take() {
+            let slot = inventory.slots().len();
+            warn!("No room to put back {} {}, added a slot for them", stack.count, stack.item);
+            let max_stack = data.items.max_stack(&stack.item);
+            inventory.ensure_slots(slot + 1);
+            inventory.place(slot, stack, max_stack);
+        }
+    }
+
+    fn draw(&self, data: &mut GameData) -> Result<(), GameError> {
+        let ui_scale = data.settings.ui_scale;
+        let screen = data.backend.screen_size();
+        let mouse = data.backend.mouse_position();
+        let inventory = &data.world.player.inventory;
+        let slots = inventory.slots().len();
+
+        draw_rectangle(0.0, 0.0, screen.x, screen.y, Color::new(0.0, 0.0, 0.0, 0.3));
+
+        let panel = Self::panel_rect(slots, ui_scale, screen);
+        draw_rounded_rect(panel.point(), panel.size(), 4.0, Color::from_rgba(0, 0, 0, 200), true, Some(HIGHLIGHT));
+        let font_size = (FONT_SIZE * ui_scale).round() as u16;
+        let title = measure_text("Inventory", Some(&data.assets.font), font_size, 1.0);
+        draw_text_ex("Inventory",
+                     panel.x + (panel.w - title.width) / 2.0,
+                     panel.y + (PADDING + TITLE_HEIGHT / 2.0) * ui_scale + title.height / 2.0,
+                     TextParams {
+                         font: Some(&data.assets.font),
+                         font_size,
+                         color: HIGHLIGHT,
+                         ..Default::default()
+                     });
+
+        let hovered = Self::slot_at(mouse, slots, ui_scale, screen);
+        for (i, stack) in inventory.slots().iter().enumerate() {
+            let rect = Self::slot_rect(i, slots, ui_scale, screen);
+            let border = if hovered == Some(i) || self.cursor == i { HIGHLIGHT } else { Color::from_rgba(0, 0, 0, 255) };
+            draw_rounded_rect(rect.point(), rect.size(), 2.0, BUTTON_FILL, true, Some(border));
+            if let Some(stack) = stack {
+                Self::draw_stack(stack, rect, ui_scale, &data.items, &data.assets);
+                if data.world.player.tool.as_deref() == Some(stack.item.as_str()) {
+                    draw_rounded_rect(rect.point(), rect.size(), 2.0, Color::from_rgba(0, 0, 0, 0), true, Some(HIGHLIGHT));
+                    Self::draw_text("E", vec2(rect.x + 9.0 * ui_scale, rect.y + 11.0 * ui_scale), false, ui_scale, &data.assets);
+                }
+            }
+        }
+
+        if let Some((stack, _)) = &self.held {
+            let size = SLOT_SIZE * ui_scale;
+            Self::draw_stack(stack, Rect::new(mouse.x - size / 2.0, mouse.y - size / 2.0, size, size), ui_scale, &data.items, &data.assets);
+        } else if let Some(stack) = hovered.and_then(|i| inventory.get(i)) {
+            let mut lines = vec![format!("{}Count: {}{}", better_term::Color::White, better_term::Color::BrightYellow, stack.count)];
+            let mut hint = "Right click to split";
+            if let Some(def) = data.items.get(&stack.item) {
+                lines.insert(0, format!("{}{}", def.rarity.color(), def.rarity.label()));
+                if let Some(tool) = &def.tool {
+                    lines.push(format!("{}Tier {}{}{}, damage {}{}{}, crit {}{}%", better_term::Color::White,
+                                       better_term::Color::BrightYellow, tool.tier, better_term::Color::White,
+                                       better_term::Color::BrightYellow, tool.damage, better_term::Color::White,
+                                       better_term::Color::BrightYellow, (tool.crit_chance * 100.0).round()));
+                    hint = if data.world.player.tool.as_deref() == Some(def.id.as_str()) {
+                        "Equipped, right click to unequip"
+                    } else {
+                        "Right click to equip"
+                    };
+                }
+                if !def.tags.is_empty() {
+                    lines.push(format!("{}{}", better_term::Color::BrightBlack, def.tags.join(", ")));
+                }
+            }
+            lines.push(format!("{}{}", better_term::Color::BrightBlack, hint));
+            let card = ToolTipCard::new(data.items.display_name(&stack.item), lines);
+            tooltip(card, &data.assets, ui_scale, data.backend.as_ref());
+        }
+
+        Ok(())
+    }
+
+    fn draw_below(&self) -> bool {
+        true
+    }
+
+    fn input_context(&self) -> InputContext {
+        InputContext::UI
+    }
+
+}
@@ -1,13 +1,25 @@
 use std::time::Duration;
+use crate::controls::InputContext;
 use crate::error::GameError;
 use crate::gamedata::GameData;
+use crate::gamestate::GameState;
 
+pub mod controls;
+pub mod inventory;
 pub mod pause;
+pub mod save_slots;
+pub mod settings;
 
 pub enum OverlayAction {
     NoOp,
     Exit,
+    /// close every overlay and go back to the gamestate
+    ExitAll,
     SpawnOverlay(Box<dyn Overlay>),
+    /// close every overlay and replace the gamestate
+    ChangeState(Box<dyn GameState>),
+    /// close the game
+    Quit,
 }
 
 pub trait Overlay {
@@ -18,6 +30,13 @@ pub trait Overlay {
     fn draw_below(&self) -> bool {
         false
     }
+    /// Called when the overlay is thrown away without exiting itself, like when the game quits,
+    /// so anything it is holding can be put back before the game saves
+    fn close(&mut self, _data: &mut GameData) {}
+    /// The set of actions this overlay listens for while it is on top
+    fn input_context(&self) -> InputContext {
+        InputContext::Menu
+    }
 }
 
 pub struct OverlayManager {
@@ -42,6 +61,12 @@ impl OverlayManager {
         self.overlays.pop();
     }
 
+    pub fn clear(&mut self, data: &mut GameData) {
+        while let Some(mut overlay) = self.overlays.pop() {
+            overlay.close(data);
+        }
+    }
+
     pub fn get_top(&self) -> Option<&Box<dyn Overlay>> {
         self.overlays.last()
     }
@@ -53,6 +78,7 @@ impl OverlayManager {
     /// returns None if there are no overlays to update (signaling to update the gamestate)
     pub fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Option<Result<OverlayAction, GameError>> {
         if let Some(overlay) = self.get_top_mut() {
+            data.control_handler.set_context(overlay.input_context());
             Some(overlay.update(delta_time, data))
         } else {
             None
@@ -1,17 +1,81 @@
 use std::time::Duration;
 
-use macroquad::{color::Color, shapes::draw_rectangle, window::{screen_height, screen_width}};
-use crate::controls::{Action, ControlHandler};
+use macroquad::{color::Color, shapes::draw_rectangle};
+use macroquad::math::vec2;
+use crate::controls::Action;
 use crate::error::GameError;
 use crate::gamedata::GameData;
+use crate::gamestate::main_menu::MainMenuGS;
 use crate::overlay::{Overlay, OverlayAction};
+use crate::overlay::controls::ControlsOverlay;
+use crate::overlay::save_slots::{SaveSlotsOverlay, SlotsMode};
+use crate::overlay::settings::SettingsOverlay;
+use crate::ui::widgets::{Ui, UiInput, UiState};
+
+const PANEL_SIZE: (f32, f32) = (240.0, 276.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PauseButton {
+    Resume,
+    Save,
+    Load,
+    Settings,
+    Controls,
+    QuitToMenu,
+    QuitToDesktop,
+}
+
+const BUTTONS: [PauseButton; 7] = [
+    PauseButton::Resume,
+    PauseButton::Save,
+    PauseButton::Load,
+    PauseButton::Settings,
+    PauseButton::Controls,
+    PauseButton::QuitToMenu,
+    PauseButton::QuitToDesktop,
+];
+
+impl PauseButton {
+    fn label(&self) -> &'static str {
+        match self {
+            PauseButton::Resume => "Resume",
+            PauseButton::Save => "Save",
+            PauseButton::Load => "Load",
+            PauseButton::Settings => "Settings",
+            PauseButton::Controls => "Controls",
+            PauseButton::QuitToMenu => "Quit to Menu",
+            PauseButton::QuitToDesktop => "Quit to Desktop",
+        }
+    }
+}
 
 #[derive(Debug)]
-pub struct PauseOverlay {}
+pub struct PauseOverlay {
+    ui: UiState,
+}
 
 impl PauseOverlay {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            ui: UiState::default(),
+        }
+    }
+
+    fn activate(&self, button: PauseButton, data: &mut GameData) -> Result<OverlayAction, GameError> {
+        Ok(match button {
+            PauseButton::Resume => OverlayAction::Exit,
+            PauseButton::Save => OverlayAction::SpawnOverlay(Box::new(SaveSlotsOverlay::new(SlotsMode::Save))),
+            PauseButton::Load => OverlayAction::SpawnOverlay(Box::new(SaveSlotsOverlay::new(SlotsMode::Load))),
+            PauseButton::Settings => OverlayAction::SpawnOverlay(Box::new(SettingsOverlay::new())),
+            PauseButton::Controls => OverlayAction::SpawnOverlay(Box::new(ControlsOverlay::new())),
+            PauseButton::QuitToMenu => {
+                // leaving for the menu keeps progress the same as closing the game does
+                data.autosave();
+                OverlayAction::ChangeState(MainMenuGS::new()?)
+            }
+            // the gamestate autosaves on the way out
+            PauseButton::QuitToDesktop => OverlayAction::Quit,
+        })
     }
 }
 
@@ -21,36 +85,34 @@ impl Overlay for PauseOverlay {
         Ok(())
     }
 
-    fn update(&mut self, _delta_time: &Duration, _data: &mut GameData) -> Result<OverlayAction, GameError> {
-        let control_handler = ControlHandler::load();
-        if let Err(e) = control_handler {
-            return Err(GameError::Update(e));
-        }
-        let control_handler = control_handler.unwrap();
-        // handle on release to ensure pause key isn't spammed when held (was an issue)
-        let actions = control_handler.get_actions();
-        for action in actions {
-            match action {
-                Action::Pause => {
-                    // if let Err(e) = self.previous_play_state.restore() {
-                    //     return Err(e);
-                    // }
-                    // pop the top state
-                    return Ok(OverlayAction::Exit);
-                }
-                // todo: UI stuff
-                _ => {
-                    // do nothing
-                }
+    fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<OverlayAction, GameError> {
+        // pause is bound as a press capture, so holding the key won't reopen the menu every frame
+        let actions = data.control_handler.get_actions(data.backend.as_ref());
+        let input = UiInput::new(actions, data);
+        let mut ui = Ui::begin(&mut self.ui, input, data.settings.ui_scale, delta_time.as_secs_f32());
+        ui.panel(vec2(PANEL_SIZE.0, PANEL_SIZE.1), "Paused");
+        let mut pressed = None;
+        for button in BUTTONS {
+            if ui.button(button.label()) {
+                pressed = Some(button);
             }
         }
+        let leftover = ui.end();
+
+        if let Some(button) = pressed {
+            return self.activate(button, data);
+        }
+        if leftover.iter().any(|a| matches!(a, Action::Pause | Action::UIBack)) {
+            return Ok(OverlayAction::Exit);
+        }
         Ok(OverlayAction::NoOp)
     }
 
-    fn draw(&self, _data: &mut GameData) -> Result<(), GameError> {
-        // draw a semi-transparent overlay
-        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(0.0, 0.0, 0.0, 0.5));
-
+    fn draw(&self, data: &mut GameData) -> Result<(), GameError> {
+        // darken the game behind the menu
+        let screen = data.backend.screen_size();
+        draw_rectangle(0.0, 0.0, screen.x, screen.y, Color::new(0.0, 0.0, 0.0, 0.5));
+        self.ui.draw(&data.assets);
         Ok(())
     }
 
@@ -58,4 +120,4 @@ impl Overlay for PauseOverlay {
         true
     }
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,163 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use macroquad::color::Color;
+use macroquad::math::vec2;
+use macroquad::shapes::draw_rectangle;
+use crate::controls::Action;
+use crate::error::GameError;
+use crate::gamedata::GameData;
+use crate::overlay::{Overlay, OverlayAction};
+use crate::save::{SaveGame, SaveSlot};
+use crate::ui::widgets::{Ui, UiInput, UiState};
+use crate::world::scene::get_scene;
+
+const PANEL_SIZE: (f32, f32) = (420.0, 272.0);
+// rows of slots shown at once before the list scrolls
+const VISIBLE_SLOTS: usize = 4;
+const MAX_NAME_LENGTH: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotsMode {
+    Save,
+    Load,
+}
+
+/// Lists the save slots to save into or load from. Saving also names the save.
+#[derive(Debug)]
+pub struct SaveSlotsOverlay {
+    mode: SlotsMode,
+    ui: UiState,
+    // each slot and what is in it
+    slots: Vec<(SaveSlot, String)>,
+    selected: usize,
+    // the name given to the next save
+    name: String,
+    // the result of the last save or load
+    message: Option<String>,
+}
+
+impl SaveSlotsOverlay {
+    pub fn new(mode: SlotsMode) -> Self {
+        Self {
+            mode,
+            ui: UiState::default(),
+            slots: Vec::new(),
+            selected: 0,
+            name: String::new(),
+            message: None,
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.slots = SaveSlot::all().into_iter()
+            // the autosave can be loaded but only the game writes to it
+            .filter(|slot| self.mode == SlotsMode::Load || *slot != SaveSlot::Auto)
+            .map(|slot| (slot, Self::describe(slot)))
+            .collect();
+    }
+
+    fn describe(slot: SaveSlot) -> String {
+        if !slot.exists() {
+            return format!("{} - Empty", slot);
+        }
+        let save = match SaveGame::read(slot) {
+            Ok(s) => s,
+            Err(_) => return format!("{} - Unreadable", slot),
+        };
+        let scene = get_scene(&save.scene).map(|s| s.name).unwrap_or("Unknown");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let minutes = now.saturating_sub(save.saved_at) / 60;
+        let age = match minutes {
+            0 => "just now".to_string(),
+            1..=59 => format!("{} min ago", minutes),
+            60..=1439 => format!("{} h ago", minutes / 60),
+            _ => format!("{} days ago", minutes / 1440),
+        };
+        match &save.name {
+            Some(name) => format!("{} - {}, {}, {}", slot, name, scene, age),
+            None => format!("{} - {}, {}", slot, scene, age),
+        }
+    }
+
+    fn activate(&mut self, index: usize, data: &mut GameData) -> OverlayAction {
+        let Some((slot, _)) = self.slots.get(index).cloned() else {
+            return OverlayAction::NoOp;
+        };
+        match self.mode {
+            SlotsMode::Save => {
+                let name = self.name.trim();
+                let name = if name.is_empty() { None } else { Some(name) };
+                self.message = Some(match data.save_game(slot, name) {
+                    Ok(_) => format!("Saved to {}", slot),
+                    Err(e) => e,
+                });
+                self.refresh();
+                OverlayAction::NoOp
+            }
+            SlotsMode::Load => {
+                if !slot.exists() {
+                    self.message = Some(format!("{} is empty", slot));
+                    return OverlayAction::NoOp;
+                }
+                match data.load_game(slot) {
+                    // straight back into the game
+                    Ok(_) => OverlayAction::ExitAll,
+                    Err(e) => {
+                        self.message = Some(e);
+                        OverlayAction::NoOp
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Overlay for SaveSlotsOverlay {
+
+    fn init(&mut self, _data: &mut GameData) -> Result<(), GameError> {
+        self.refresh();
+        Ok(())
+    }
+
+    fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<OverlayAction, GameError> {
+        let actions = data.control_handler.get_actions(data.backend.as_ref());
+        let input = UiInput::new(actions, data);
+        let mut ui = Ui::begin(&mut self.ui, input, data.settings.ui_scale, delta_time.as_secs_f32());
+        let title = match self.mode {
+            SlotsMode::Save => "Save Game",
+            SlotsMode::Load => "Load Game",
+        };
+        ui.panel(vec2(PANEL_SIZE.0, PANEL_SIZE.1), title);
+
+        if self.mode == SlotsMode::Save {
+            ui.text_input("Name", &mut self.name, MAX_NAME_LENGTH);
+        }
+        let labels: Vec<String> = self.slots.iter().map(|(_, label)| label.clone()).collect();
+        let activated = ui.scroll_list(&labels, &mut self.selected, VISIBLE_SLOTS);
+        let back = ui.button("Back");
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        let leftover = ui.end();
+
+        if let Some(index) = activated {
+            return Ok(self.activate(index, data));
+        }
+        if back || leftover.iter().any(|a| matches!(a, Action::Pause | Action::UIBack)) {
+            return Ok(OverlayAction::Exit);
+        }
+        Ok(OverlayAction::NoOp)
+    }
+
+    fn draw(&self, data: &mut GameData) -> Result<(), GameError> {
+        let screen = data.backend.screen_size();
+        draw_rectangle(0.0, 0.0, screen.x, screen.y, Color::new(0.0, 0.0, 0.0, 0.5));
+        self.ui.draw(&data.assets);
+        Ok(())
+    }
+
+    fn draw_below(&self) -> bool {
+        true
+    }
+
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use macroquad::color::Color;
+use macroquad::math::vec2;
+use macroquad::shapes::draw_rectangle;
+use crate::controls::Action;
+use crate::error::GameError;
+use crate::warn;
+use crate::gamedata::GameData;
+use crate::overlay::{Overlay, OverlayAction};
+use crate::settings::{Settings, MAX_UI_SCALE, MIN_UI_SCALE};
+use crate::ui::popup::{PopupCard, PopupLocation};
+use crate::ui::widgets::{Ui, UiInput, UiState};
+
+const VOLUME_STEP: f32 = 0.1;
+const UI_SCALE_STEP: f32 = 0.25;
+
+// window sizes offered in the dropdown, the current size is added if it was set by hand
+const WINDOW_SIZES: [(i32, i32); 4] = [(1000, 700), (1280, 720), (1600, 900), (1920, 1080)];
+
+const PANEL_SIZE: (f32, f32) = (420.0, 284.0);
+// between the settings and the buttons
+const BUTTON_GAP: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsButton {
+    Apply,
+    Cancel,
+    Done,
+}
+
+const BUTTONS: [SettingsButton; 3] = [
+    SettingsButton::Apply,
+    SettingsButton::Cancel,
+    SettingsButton::Done,
+];
+
+impl SettingsButton {
+    fn label(&self) -> &'static str {
+        match self {
+            SettingsButton::Apply => "Apply",
+            SettingsButton::Cancel => "Cancel",
+            SettingsButton::Done => "Done",
+        }
+    }
+}
+
+/// Edits `GameData::settings` in place so changes preview immediately.
+/// Apply commits the current values to disk, Cancel reverts to the last committed values,
+/// and leaving the menu any other way commits and saves.
+#[derive(Debug)]
+pub struct SettingsOverlay {
+    committed: Option<Settings>,
+    committed_sprint_toggle: bool,
+    ui: UiState,
+}
+
+impl SettingsOverlay {
+    pub fn new() -> Self {
+        Self {
+            committed: None,
+            committed_sprint_toggle: false,
+            ui: UiState::default(),
+        }
+    }
+
+    fn commit(&mut self, data: &mut GameData) {
+        data.settings.save();
+        let changed = self.committed.as_ref() != Some(&data.settings)
+            || self.committed_sprint_toggle != data.control_handler.is_sprint_toggle();
+        if changed {
+            data.notifications.push(PopupCard::new("Settings saved".to_string(), vec![], PopupLocation::Top));
+        }
+        self.committed = Some(data.settings.clone());
+        self.committed_sprint_toggle = data.control_handler.is_sprint_toggle();
+    }
+
+    fn revert(&mut self, data: &mut GameData) {
+        let Some(committed) = &self.committed else {
+            return;
+        };
+        if committed.fullscreen != data.settings.fullscreen || committed.window_size != data.settings.window_size {
+            committed.apply_graphics();
+        }
+        data.settings = committed.clone();
+        if data.control_handler.is_sprint_toggle() != self.committed_sprint_toggle {
+            let result = data.control_handler.set_sprint_toggle(self.committed_sprint_toggle);
+            Self::report_controls_error(result, data);
+        }
+    }
+
+    fn window_sizes(current: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut sizes = WINDOW_SIZES.to_vec();
+        if !sizes.contains(&current) {
+            sizes.push(current);
+        }
+        sizes
+    }
+
+    fn report_controls_error(result: Result<(), String>, data: &mut GameData) {
+        if let Err(e) = result {
+            warn!("{}", e);
+            data.notifications.push(PopupCard::new("Couldn't save controls".to_string(), vec![e], PopupLocation::Top));
+        }
+    }
+
+    fn activate_button(&mut self, button: SettingsButton, data: &mut GameData) -> OverlayAction {
+        match button {
+            SettingsButton::Apply => {
+                self.commit(data);
+                OverlayAction::NoOp
+            }
+            SettingsButton::Cancel => {
+                self.revert(data);
+                OverlayAction::Exit
+            }
+            SettingsButton::Done => {
+                self.commit(data);
+                OverlayAction::Exit
+            }
+        }
+    }
+}
+
+impl Overlay for SettingsOverlay {
+
+    fn init(&mut self, data: &mut GameData) -> Result<(), GameError> {
+        self.committed = Some(data.settings.clone());
+        self.committed_sprint_toggle = data.control_handler.is_sprint_toggle();
+        Ok(())
+    }
+
+    fn update(&mut self, delta_time: &Duration, data: &mut GameData) -> Result<OverlayAction, GameError> {
+        let actions = data.control_handler.get_actions(data.backend.as_ref());
+        let input = UiInput::new(actions, data);
+        let mut ui = Ui::begin(&mut self.ui, input, data.settings.ui_scale, delta_time.as_secs_f32());
+        ui.panel(vec2(PANEL_SIZE.0, PANEL_SIZE.1), "Settings");
+
+        let settings = &mut data.settings;
+        ui.slider("Volume", &mut settings.volume, 0.0..=1.0, VOLUME_STEP, |v| format!("{}%", (v * 100.0).round()));
+        ui.toggle("Mute", &mut settings.mute);
+        if ui.toggle("Fullscreen", &mut settings.fullscreen) {
+            settings.apply_graphics();
+        }
+        let sizes = Self::window_sizes(settings.window_size);
+        let labels: Vec<String> = sizes.iter().map(|(w, h)| format!("{}x{}", w, h)).collect();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        let mut selected = sizes.iter().position(|s| *s == settings.window_size).unwrap_or_default();
+        if ui.dropdown("Window Size", &labels, &mut selected) {
+            settings.window_size = sizes[selected];
+            settings.apply_graphics();
+        }
+        ui.slider("UI Scale", &mut settings.ui_scale, MIN_UI_SCALE..=MAX_UI_SCALE, UI_SCALE_STEP, |v| format!("{:.2}x", v));
+        let mut sprint_toggle = data.control_handler.is_sprint_toggle();
+        if ui.toggle("Toggle Sprint", &mut sprint_toggle) {
+            let result = data.control_handler.set_sprint_toggle(sprint_toggle);
+            Self::report_controls_error(result, data);
+        }
+
+        ui.space(BUTTON_GAP);
+        ui.columns(BUTTONS.len());
+        let mut pressed = None;
+        for button in BUTTONS {
+            if ui.button(button.label()) {
+                pressed = Some(button);
+            }
+        }
+        let leftover = ui.end();
+
+        if let Some(button) = pressed {
+            return Ok(self.activate_button(button, data));
+        }
+        if leftover.iter().any(|a| matches!(a, Action::Pause | Action::UIBack)) {
+            // leaving the menu keeps the previewed changes
+            self.commit(data);
+            return Ok(OverlayAction::Exit);
+        }
+        Ok(OverlayAction::NoOp)
+    }
+
+    fn draw(&self, data: &mut GameData) -> Result<(), GameError> {
+        let screen = data.backend.screen_size();
+        draw_rectangle(0.0, 0.0, screen.x, screen.y, Color::new(0.0, 0.0, 0.0, 0.5));
+        self.ui.draw(&data.assets);
+        Ok(())
+    }
+
+    fn draw_below(&self) -> bool {
+        true
+    }
+
+}
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use macroquad::input::MouseButton;
+use macroquad::window::{screen_height, screen_width};
+use serde::{Deserialize, Serialize};
+use crate::controls::{Action, InputContext};
+use crate::{info, warn};
+
+/*
+Replays are stored as JSON lines: a header, then one line per frame.
+Frames are written as they happen so a recording survives the game being closed or crashing.
+
+Run with `--record <file>` to record a session and `--replay <file>` to play it back.
+While a replay is playing, every action, analog strength, mouse position and button, scroll,
+typed character and frame time comes from the file instead of the live input, so the session plays out frame-for-frame.
+*/
+
+/// Bump this when the layout of a replay file changes
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReplayHeader {
+    version: u32,
+    // the random seed the session ran with, so rocks spawn in the same places
+    seed: u64,
+    screen_size: (f32, f32),
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Frame {
+    delta_seconds: f32,
+    mouse: (f32, f32),
+    wheel: f32,
+    // the mouse buttons held down, by `button_id`
+    mouse_buttons: Vec<u8>,
+    // characters typed into text boxes, in order
+    typed: Vec<char>,
+    // the result of `get_actions` for each context that asked this frame
+    actions: HashMap<InputContext, Vec<Action>>,
+    // only actions pressed at all are stored, anything missing was 0.0
+    strengths: HashMap<Action, f32>,
+    gamepad_actions: Vec<Action>,
+}
+
+enum Mode {
+    Recording {
+        writer: BufWriter<File>,
+        frame: Option<Frame>,
+    },
+    Playing {
+        frames: VecDeque<Frame>,
+        frame: Frame,
+        // the buttons held on the frame before, to tell presses and releases apart
+        last_mouse_buttons: Vec<u8>,
+    },
+}
+
+const RECORDED_BUTTONS: [MouseButton; 3] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+fn button_id(button: MouseButton) -> u8 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Right => 1,
+        MouseButton::Middle => 2,
+        MouseButton::Unknown => 3,
+    }
+}
+
+static STATE: Mutex<Option<Mode>> = Mutex::new(None);
+
+fn with_state<R>(f: impl FnOnce(&mut Option<Mode>) -> R) -> R {
+    let mut guard = STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut guard)
+}
+
+/// Start recording or playing back a replay if one was asked for on the command line.
+/// Must be called before the world is created so it spawns with the replay's random seed.
+pub fn start_from_args() -> Result<(), String> {
+    let args = std::env::args().collect::<Vec<String>>();
+    let path_after = |flag: &str| args.iter()
+        .position(|a| a == flag)
+        .map(|i| args.get(i + 1).cloned().ok_or(format!("{} needs a file path", flag)));
+
+    if let Some(path) = path_after("--replay") {
+        return start_playback(Path::new(&path?));
+    }
+    if let Some(path) = path_after("--record") {
+        return start_recording(Path::new(&path?));
+    }
+    Ok(())
+}
+
+pub fn start_recording(path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            return Err(format!("Failed to create replay directory: {}", e));
+        }
+    }
+    let file = match File::create(path) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("Failed to create replay file {}: {}", path.display(), e)),
+    };
+
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    macroquad::rand::srand(seed);
+
+    let header = ReplayHeader {
+        version: REPLAY_VERSION,
+        seed,
+        screen_size: (screen_width(), screen_height()),
+    };
+    let mut writer = BufWriter::new(file);
+    write_line(&mut writer, &header)?;
+
+    info!("Recording input to {}", path.display());
+    with_state(|state| *state = Some(Mode::Recording { writer, frame: None }));
+    Ok(())
+}
+
+pub fn start_playback(path: &Path) -> Result<(), String> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("Failed to open replay file {}: {}", path.display(), e)),
+    };
+    let mut lines = BufReader::new(file).lines();
+
+    let Some(Ok(header)) = lines.next() else {
+        return Err(format!("Replay file {} is empty", path.display()));
+    };
+    let header = match serde_json::from_str::<ReplayHeader>(&header) {
+        Ok(h) => h,
+        Err(e) => return Err(format!("Failed to read replay header: {}", e)),
+    };
+    if header.version != REPLAY_VERSION {
+        return Err(format!("Replay is version {}, but this build plays version {}", header.version, REPLAY_VERSION));
+    }
+    if header.screen_size != (screen_width(), screen_height()) {
+        warn!("Replay was recorded at {:?}, playback may differ at the current window size", header.screen_size);
+    }
+
+    let mut frames = VecDeque::new();
+    for (i, line) in lines.enumerate() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => return Err(format!("Failed to read replay frame {}: {}", i, e)),
+        };
+        match serde_json::from_str::<Frame>(&line) {
+            Ok(frame) => frames.push_back(frame),
+            // the last line can be cut off if the game was killed while recording
+            Err(e) => {
+                warn!("Stopping replay at frame {}, it could not be read: {}", i, e);
+                break;
+            }
+        }
+    }
+
+    macroquad::rand::srand(header.seed);
+    info!("Playing back {} frames from {}", frames.len(), path.display());
+    with_state(|state| *state = Some(Mode::Playing { frames, frame: Frame::default(), last_mouse_buttons: Vec::new() }));
+    Ok(())
+}
+
+fn write_line<T: Serialize>(writer: &mut BufWriter<File>, value: &T) -> Result<(), String> {
+    let Ok(serialized) = serde_json::to_string(value) else {
+        return Err("Failed to serialize replay data".to_string());
+    };
+    if let Err(e) = writeln!(writer, "{}", serialized).and_then(|_| writer.flush()) {
+        return Err(format!("Failed to write replay: {}", e));
+    }
+    Ok(())
+}
+
+/// Move the replay on to the next frame. Must be called once per frame before input is checked.
+/// Returns the delta time the frame should use, which is the recorded one while playing back.
+pub fn begin_frame(delta_seconds: f32) -> f32 {
+    with_state(|state| match state {
+        Some(Mode::Recording { writer, frame }) => {
+            if let Some(finished) = frame.take() {
+                if let Err(e) = write_line(writer, &finished) {
+                    warn!("{}, recording stopped", e);
+                    *state = None;
+                    return delta_seconds;
+                }
+            }
+            let mouse = macroquad::input::mouse_position();
+            let (_, wheel) = macroquad::input::mouse_wheel();
+            let mouse_buttons = RECORDED_BUTTONS.into_iter()
+                .filter(|b| macroquad::input::is_mouse_button_down(*b))
+                .map(button_id)
+                .collect();
+            *frame = Some(Frame { delta_seconds, mouse, wheel, mouse_buttons, ..Default::default() });
+            delta_seconds
+        }
+        Some(Mode::Playing { frames, frame, last_mouse_buttons }) => match frames.pop_front() {
+            Some(next) => {
+                *last_mouse_buttons = std::mem::replace(frame, next).mouse_buttons;
+                frame.delta_seconds
+            }
+            None => {
+                info!("Replay finished, returning to live input");
+                *state = None;
+                delta_seconds
+            }
+        },
+        None => delta_seconds,
+    })
+}
+
+/// Stop the replay, writing the frame still being recorded so the end of the session isn't lost
+pub fn finish() {
+    with_state(|state| {
+        if let Some(Mode::Recording { writer, frame: Some(finished) }) = state {
+            if let Err(e) = write_line(writer, finished) {
+                warn!("{}, the last frame was not recorded", e);
+            }
+        }
+        *state = None;
+    })
+}
+
+fn recording(f: impl FnOnce(&mut Frame)) {
+    with_state(|state| {
+        if let Some(Mode::Recording { frame: Some(frame), .. }) = state {
+            f(frame);
+        }
+    })
+}
+
+fn playing<R>(f: impl FnOnce(&Frame) -> R) -> Option<R> {
+    with_state(|state| match state {
+        Some(Mode::Playing { frame, .. }) => Some(f(frame)),
+        _ => None,
+    })
+}
+
+/// Whether a replay is recording or playing back
+pub fn is_active() -> bool {
+    with_state(|state| state.is_some())
+}
+
+/// The mouse position, from the replay while one is playing
+pub fn mouse_position() -> (f32, f32) {
+    playing(|frame| frame.mouse).unwrap_or_else(macroquad::input::mouse_position)
+}
+
+/// The vertical mouse wheel movement, from the replay while one is playing
+pub fn mouse_wheel() -> f32 {
+    playing(|frame| frame.wheel).unwrap_or_else(|| macroquad::input::mouse_wheel().1)
+}
+
+/// Whether a mouse button is down this frame and whether it was down last frame, while a replay is playing
+pub fn mouse_button(button: MouseButton) -> Option<(bool, bool)> {
+    let id = button_id(button);
+    with_state(|state| match state {
+        Some(Mode::Playing { frame, last_mouse_buttons, .. }) => {
+            Some((frame.mouse_buttons.contains(&id), last_mouse_buttons.contains(&id)))
+        }
+        _ => None,
+    })
+}
+
+/// Characters typed since the last call, from the replay while one is playing
+pub fn typed_chars() -> Vec<char> {
+    let live = || std::iter::from_fn(macroquad::input::get_char_pressed).collect::<Vec<char>>();
+    with_state(|state| match state {
+        // taken so each recorded character is only typed once
+        Some(Mode::Playing { frame, .. }) => std::mem::take(&mut frame.typed),
+        Some(Mode::Recording { frame: Some(frame), .. }) => {
+            let typed = live();
+            frame.typed.extend(&typed);
+            typed
+        }
+        _ => live(),
+    })
+}
+
+pub fn record_actions(context: InputContext, actions: &[Action]) {
+    recording(|frame| { frame.actions.insert(context, actions.to_vec()); });
+}
+
+pub fn recorded_actions(context: InputContext) -> Option<Vec<Action>> {
+    playing(|frame| frame.actions.get(&context).cloned().unwrap_or_default())
+}
+
+pub fn record_strength(action: &Action, strength: f32) {
+    if strength > 0.0 {
+        recording(|frame| { frame.strengths.insert(action.clone(), strength); });
+    }
+}
+
+pub fn recorded_strength(action: &Action) -> Option<f32> {
+    playing(|frame| frame.strengths.get(action).copied().unwrap_or(0.0))
+}
+
+pub fn record_gamepad_action(action: &Action, active: bool) {
+    if active {
+        recording(|frame| if !frame.gamepad_actions.contains(action) {
+            frame.gamepad_actions.push(action.clone());
+        });
+    }
+}
+
+pub fn recorded_gamepad_action(action: &Action) -> Option<bool> {
+    playing(|frame| frame.gamepad_actions.contains(action))
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use macroquad::math::vec2;
+use serde::{Deserialize, Serialize};
+use crate::assets::GlobalAssets;
+use crate::{info, warn};
+use crate::world::interactable::{Interactable, InteractableAttribute};
+use crate::world::inventory::{Inventory, INVENTORY_SLOTS};
+use crate::world::player::{Player, PlayerFacing};
+use crate::world::portal::Portal;
+use crate::world::rock::Rock;
+use crate::world::scene::load_scene;
+use crate::world::World;
+
+const SAVE_DIR: &str = "./data/saves";
+
+/// Bump this when the layout of save files changes, and teach `SaveGame::migrate` about the old one.
+/// Version 2 added the player's inventory.
+/// Version 3 added the equipped tool.
+/// Version 4 added names for saves.
+pub const SAVE_VERSION: u32 = 4;
+
+/// How many slots the player can save into by hand, on top of the autosave
+pub const MANUAL_SLOTS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveSlot {
+    Auto,
+    /// numbered from 1
+    Manual(usize),
+}
+
+impl SaveSlot {
+    /// The autosave first, then every manual slot
+    pub fn all() -> Vec<SaveSlot> {
+        let mut slots = vec![SaveSlot::Auto];
+        slots.extend((1..=MANUAL_SLOTS).map(SaveSlot::Manual));
+        slots
+    }
+
+    fn path(&self) -> PathBuf {
+        let file = match self {
+            SaveSlot::Auto => "autosave.dat".to_string(),
+            SaveSlot::Manual(n) => format!("slot{}.dat", n),
+        };
+        PathBuf::from(SAVE_DIR).join(file)
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path().exists()
+    }
+}
+
+impl fmt::Display for SaveSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveSlot::Auto => write!(f, "Autosave"),
+            SaveSlot::Manual(n) => write!(f, "Slot {}", n),
+        }
+    }
+}
+
+/// The slot that was saved to most recently, if there are any saves
+pub fn latest_slot() -> Option<SaveSlot> {
+    SaveSlot::all().into_iter()
+        .filter(|slot| slot.exists())
+        .filter_map(|slot| SaveGame::read(slot).ok().map(|save| (slot, save.saved_at)))
+        .max_by_key(|(_, saved_at)| *saved_at)
+        .map(|(slot, _)| slot)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub pos: (f32, f32),
+    pub facing: PlayerFacing,
+    pub sprinting: bool,
+    // version 1 saves have no inventory, they load with an empty one
+    #[serde(default)]
+    pub inventory: Inventory,
+    // saves before version 3 have no tool, the player starts with their fists
+    #[serde(default)]
+    pub tool: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InteractableSave {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub id: u32,
+    pub name: String,
+    pub pos: (f32, f32),
+    pub rotation: f32,
+    pub attributes: HashMap<String, InteractableAttribute>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneSave {
+    pub interactables: Vec<InteractableSave>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    /// seconds since the unix epoch
+    pub saved_at: u64,
+    /// what the player called the save, saves before version 4 and autosaves have none
+    #[serde(default)]
+    pub name: Option<String>,
+    /// the scene the player is in
+    pub scene: String,
+    pub player: PlayerSave,
+    /// every scene that has been visited, including the current one
+    pub scenes: HashMap<String, SceneSave>,
+}
+
+impl SaveGame {
+    /// Capture the current world and every visited scene
+    pub fn capture<'a>(name: Option<&str>, scene: &str, world: &World, visited: impl Iterator<Item = (&'a String, &'a World)>) -> Self {
+        let player = &world.player;
+        let mut scenes = HashMap::new();
+        scenes.insert(scene.to_string(), save_world(world));
+        for (id, world) in visited {
+            scenes.insert(id.clone(), save_world(world));
+        }
+
+        Self {
+            version: SAVE_VERSION,
+            saved_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            name: name.map(str::to_string),
+            scene: scene.to_string(),
+            player: PlayerSave {
+                pos: (player.pos.x, player.pos.y),
+                facing: player.facing.clone(),
+                sprinting: player.sprinting,
+                inventory: player.inventory.clone(),
+                tool: player.tool.clone(),
+            },
+            scenes,
+        }
+    }
+
+    pub fn write(&self, slot: SaveSlot) -> Result<(), String> {
+        let path = slot.path();
+        if let Err(e) = std::fs::create_dir_all(SAVE_DIR) {
+            return Err(format!("Failed to create save directory: {}", e));
+        }
+        let Ok(serialized) = serde_json::to_string_pretty(self) else {
+            return Err("Failed to serialize save game".to_string());
+        };
+        // write next to the old save and swap it in, so a crash mid write can't eat the slot
+        let temp = path.with_extension("dat.tmp");
+        if let Err(e) = std::fs::write(&temp, serialized) {
+            return Err(format!("Failed to write {}: {}", temp.display(), e));
+        }
+        if let Err(e) = std::fs::rename(&temp, &path) {
+            return Err(format!("Failed to replace {}: {}", path.display(), e));
+        }
+        Ok(())
+    }
+
+    pub fn read(slot: SaveSlot) -> Result<Self, String> {
+        let path = slot.path();
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let save = match serde_json::from_str::<SaveGame>(&contents) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{} is corrupt: {}", path.display(), e)),
+        };
+        save.migrate()
+    }
+
+    /// Bring a save from an older version of the game up to date
+    fn migrate(mut self) -> Result<Self, String> {
+        if self.version > SAVE_VERSION {
+            return Err(format!("This save is from a newer version of the game (save version {}, expected {})", self.version, SAVE_VERSION));
+        }
+        if self.version < SAVE_VERSION {
+            info!("Upgrading save from version {} to {}", self.version, SAVE_VERSION);
+            self.version = SAVE_VERSION;
+        }
+        Ok(self)
+    }
+
+    /// Rebuild every saved scene. Returns the current scene's world first, then the others by id.
+    pub fn restore(&self, assets: &GlobalAssets) -> Result<(World, HashMap<String, World>), String> {
+        let mut worlds = HashMap::new();
+        for (id, scene) in &self.scenes {
+            let mut world = load_scene(id, assets)?;
+            world.interactables = scene.interactables.iter()
+                .map(|i| restore_interactable(assets, i))
+                .collect::<Result<Vec<_>, String>>()?;
+            worlds.insert(id.clone(), world);
+        }
+
+        let Some(mut world) = worlds.remove(&self.scene) else {
+            return Err(format!("Save has no state for its current scene {}", self.scene));
+        };
+        let mut player = match Player::new() {
+            Ok(p) => p,
+            Err(e) => return Err(format!("Failed to initialize player: {}", e)),
+        };
+        player.pos = vec2(self.player.pos.0, self.player.pos.1);
+        player.prev_pos = player.pos;
+        player.facing = self.player.facing.clone();
+        player.sprinting = self.player.sprinting;
+        player.inventory = self.player.inventory.clone();
+        // a hand edited save can have too few slots to show or put anything in
+        if player.inventory.ensure_slots(INVENTORY_SLOTS) {
+            warn!("Save's inventory had fewer than {} slots, added empty ones", INVENTORY_SLOTS);
+        }
+        player.tool = self.player.tool.clone();
+        world.player = player;
+
+        Ok((world, worlds))
+    }
+}
+
+fn save_world(world: &World) -> SceneSave {
+    SceneSave {
+        interactables: world.interactables.iter().map(|i| save_interactable(i.as_ref())).collect(),
+    }
+}
+
+fn save_interactable(interactable: &dyn Interactable) -> InteractableSave {
+    let pos = interactable.get_pos();
+    InteractableSave {
+        ty: interactable.get_type().to_string(),
+        id: interactable.get_id(),
+        name: interactable.get_name(),
+        pos: (pos.x, pos.y),
+        rotation: interactable.get_rotation(),
+        attributes: interactable.attribute_names().iter()
+            .filter_map(|name| interactable.get_attribute(name).map(|a| (name.to_string(), a)))
+            .collect(),
+    }
+}
+
+/// Build an interactable of the saved type, then put its attributes back
+fn restore_interactable(assets: &GlobalAssets, saved: &InteractableSave) -> Result<Box<dyn Interactable>, String> {
+    let pos = vec2(saved.pos.0, saved.pos.1);
+    let mut interactable: Box<dyn Interactable> = match saved.ty.as_str() {
+        "rock" => {
+            // saves from before rock kinds were all plain stone
+            let kind = match saved.attributes.get("kind") {
+                Some(InteractableAttribute::String(kind)) => kind.as_str(),
+                _ => "stone",
+            };
+            Box::new(Rock::new(assets, saved.id, kind, Some(saved.name.clone()), pos, saved.rotation))
+        }
+        "portal" => Box::new(Portal::new(assets, saved.id, saved.name.clone(), pos, String::new(), None, 0)),
+        other => return Err(format!("Unknown interactable type `{}` in save", other)),
+    };
+    for (name, value) in &saved.attributes {
+        if let Err(e) = interactable.set_attribute(name, value.clone()) {
+            return Err(format!("Failed to restore {} {}: {}", saved.ty, saved.id, e));
+        }
+    }
+    Ok(interactable)
+}
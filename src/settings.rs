@@ -1,28 +1,144 @@
+use std::path::Path;
+use macroquad::window::{request_new_screen_size, set_fullscreen};
+use serde::{Deserialize, Serialize};
+use crate::{warn, BASE_WINDOW_SIZE};
 
+const SETTINGS_PATH: &str = "./data/settings.dat";
 
+/// Bump this when the layout of the settings file changes
+pub const SETTINGS_VERSION: u32 = 1;
+
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 3.0;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Settings {
+    pub version: u32,
+
     // graphics
-    // todo
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub window_size: (i32, i32),
+    pub ui_scale: f32,
 
     // audio
     pub volume: f32,
     pub mute: bool,
-    // todo
 
     // gameplay
-    // todo
+    pub show_tooltips: bool,
+    pub show_fps: bool,
 
     // -- controls handled separately
 }
 
 impl Settings {
 
+    /// Create the default settings file if it doesn't exist
+    fn create_default_settings() -> Result<(), String> {
+        Settings::default().write(Path::new(SETTINGS_PATH))
+    }
+
+    /// Load the settings from the file, creating it if it doesn't exist.
+    /// A corrupt settings file is backed up and replaced with the defaults instead of failing startup.
     pub fn load() -> Self {
-        todo!()
+        let path = Path::new(SETTINGS_PATH);
+
+        if !path.exists() {
+            if let Err(e) = Self::create_default_settings() {
+                warn!("{}", e);
+            }
+            return Self::default();
+        }
+
+        let contents = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Failed to read {}: {}. Using default settings.", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        match serde_json::from_str::<Settings>(&contents) {
+            Ok(mut settings) => {
+                if settings.version != SETTINGS_VERSION {
+                    // missing fields are filled in by serde, so just bring the file up to date
+                    settings.version = SETTINGS_VERSION;
+                    settings.save();
+                }
+                settings.sanitize();
+                settings
+            }
+            Err(e) => {
+                warn!("Settings file is corrupt ({}), restoring defaults.", e);
+                let backup = path.with_extension("dat.bak");
+                if let Err(e) = std::fs::rename(path, &backup) {
+                    warn!("Failed to back up corrupt settings to {}: {}", backup.display(), e);
+                }
+                if let Err(e) = Self::create_default_settings() {
+                    warn!("{}", e);
+                }
+                Self::default()
+            }
+        }
     }
 
+    /// Save the settings to the file
     pub fn save(&self) {
-        todo!()
+        if let Err(e) = self.write(Path::new(SETTINGS_PATH)) {
+            warn!("{}", e);
+        }
     }
 
-}
\ No newline at end of file
+    fn write(&self, path: &Path) -> Result<(), String> {
+        // make the directories
+        if let Err(e) = std::fs::create_dir_all(path.parent().unwrap()) {
+            return Err(format!("Failed to create data directory: {}", e));
+        }
+
+        let Ok(serialized) = serde_json::to_string_pretty(self) else {
+            return Err(format!("Failed to serialize settings. Delete {} to regenerate.", path.display()));
+        };
+
+        if let Err(e) = std::fs::write(path, serialized) {
+            return Err(format!("Failed to write settings to file: {}", e));
+        }
+
+        Ok(())
+    }
+
+    /// Push the graphics settings that can change at runtime to the window
+    pub fn apply_graphics(&self) {
+        set_fullscreen(self.fullscreen);
+        if !self.fullscreen {
+            request_new_screen_size(self.window_size.0 as f32, self.window_size.1 as f32);
+        }
+    }
+
+    /// Clamp values that were hand edited into something unusable
+    fn sanitize(&mut self) {
+        self.volume = self.volume.clamp(0.0, 1.0);
+        self.ui_scale = self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        if self.window_size.0 < 320 || self.window_size.1 < 240 {
+            self.window_size = BASE_WINDOW_SIZE;
+        }
+    }
+
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            fullscreen: false,
+            vsync: true,
+            window_size: BASE_WINDOW_SIZE,
+            ui_scale: 1.0,
+            volume: 1.0,
+            mute: false,
+            show_tooltips: true,
+            show_fps: false,
+        }
+    }
+}
@@ -1,10 +1,17 @@
 use macroquad::color::{Color, DARKGRAY, GREEN};
 use macroquad::prelude::{clear_background, draw_texture_ex, get_time, next_frame, screen_height, screen_width, vec2, DrawTextureParams, FilterMode, Texture2D, BLACK, WHITE};
 use crate::assets;
+use crate::backend::WindowBackend;
+use crate::camera::Camera;
 use crate::error::GameError;
 use crate::gamedata::GameData;
+use crate::item::{ItemRegistry, ITEMS_FILE};
+use crate::loot::{self, LootTables, LOOT_FILE};
 use crate::settings::Settings;
+use crate::ui::popup::Notifications;
 use crate::util::draw_rounded_rect;
+use crate::world::scene::{self, SceneManager};
+use crate::{info, warn};
 
 const BANANA_BYTES: &[u8] = include_bytes!("../assets/sprites/engine_logo.png");
 
@@ -72,7 +79,7 @@ async fn draw_loading_screen_frame(percentage: usize, banana_texture: &Texture2D
     next_frame().await;
 }
 
-pub async fn startup_loading_screen() -> Result<GameData, GameError> {
+pub async fn startup_loading_screen(settings: Settings) -> Result<GameData, GameError> {
     let banana_texture = Texture2D::from_file_with_format(BANANA_BYTES, Some(macroquad::prelude::ImageFormat::Png));
     banana_texture.set_filter(FilterMode::Nearest);
 
@@ -88,12 +95,45 @@ pub async fn startup_loading_screen() -> Result<GameData, GameError> {
 
     draw_loading_screen_frame(2, &banana_texture, Some(&assets.player_sprite)).await;
 
-    //let settings = crate::settings::Settings::load();
-    let settings = Settings {
-        volume: 1.0,
-        mute: false,
+    let items = match ItemRegistry::from_json(ITEMS_FILE, &assets) {
+        Ok((items, errors)) => {
+            // a broken item shouldn't stop the game, it just won't exist
+            for e in errors {
+                warn!("Skipped item: {}", e);
+            }
+            info!("Loaded {} items", items.count());
+            items
+        }
+        Err(e) => {
+            return Err(GameError::Initialization(format!("Failed to load items: {}", e)));
+        }
+    };
+
+    let loot = match LootTables::from_json(LOOT_FILE, &items) {
+        Ok((loot, errors)) => {
+            for e in errors {
+                warn!("Skipped loot table: {}", e);
+            }
+            for rock in assets.rocks.iter() {
+                if let Some(table) = rock.loot.as_deref().filter(|t| !loot.contains(t)) {
+                    warn!("Rock `{}` drops from table `{}`, which doesn't exist", rock.id, table);
+                }
+            }
+            info!("Loaded {} loot tables", loot.count());
+            loot
+        }
+        Err(e) => {
+            return Err(GameError::Initialization(format!("Failed to load loot tables: {}", e)));
+        }
+    };
+    let loot_rng = match loot::rng_from_args() {
+        Ok(r) => r,
+        Err(e) => {
+            return Err(GameError::Initialization(e));
+        }
     };
 
+
     let control_handler = match crate::controls::ControlHandler::load() {
         Ok(c) => c,
         Err(e) => {
@@ -103,7 +143,8 @@ pub async fn startup_loading_screen() -> Result<GameData, GameError> {
 
     draw_loading_screen_frame(5, &banana_texture,Some(&assets.player_sprite)).await;
 
-    let world = match crate::world::World::new(&assets).await {
+    let backend = WindowBackend::new();
+    let (scene, world) = match scene::start_from_args(&assets) {
         Ok(w) => w,
         Err(e) => {
             return Err(GameError::Initialization(format!("Failed to create world: {}", e)));
@@ -125,8 +166,17 @@ pub async fn startup_loading_screen() -> Result<GameData, GameError> {
     Ok(GameData {
         fps: 0.0,
         assets,
+        items,
+        loot,
+        loot_rng,
         settings,
         control_handler,
+        camera: Camera::new(world.player.pos),
+        notifications: Notifications::default(),
         world,
+        scenes: SceneManager::new(scene),
+        backend: Box::new(backend),
+        interpolation: 0.0,
+        controls_check_timer: 0.0,
     })
 }
\ No newline at end of file
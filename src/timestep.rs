@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+/// How long each fixed update simulates, 60 updates per second
+pub const FIXED_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Stop catching up after this many fixed updates in one frame, so a long hitch doesn't snowball
+pub const MAX_FIXED_STEPS_PER_FRAME: u32 = 8;
+
+/// Splits frame times into fixed updates, carrying leftover time over to the next frame
+/// so the simulation runs the same no matter how the time is split up
+#[derive(Debug, Default)]
+pub struct FixedTimestep {
+    // time that has passed but not been simulated by a fixed update yet
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    /// Add a frame's time, returning how many fixed updates to run for it
+    pub fn advance(&mut self, delta_time: Duration) -> u32 {
+        self.accumulator += delta_time;
+        let mut steps = 0;
+        while self.accumulator >= FIXED_TIMESTEP {
+            if steps == MAX_FIXED_STEPS_PER_FRAME {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            self.accumulator -= FIXED_TIMESTEP;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Drop any leftover time, for when the state being simulated changes
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+    }
+
+    /// How far the frame is between the last fixed update and the next one, from 0.0 to 1.0
+    pub fn interpolation(&self) -> f32 {
+        self.accumulator.as_secs_f32() / FIXED_TIMESTEP.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_steps(frames: &[f64]) -> u32 {
+        let mut timestep = FixedTimestep::default();
+        frames.iter().map(|seconds| timestep.advance(Duration::from_secs_f64(*seconds))).sum()
+    }
+
+    #[test]
+    fn same_time_runs_the_same_steps() {
+        let thirty = vec![1.0 / 30.0; 30];
+        let sixty = vec![1.0 / 60.0; 60];
+        let uneven = [0.01, 1.0 / 30.0 - 0.01].repeat(30);
+        assert_eq!(total_steps(&thirty), 60);
+        assert_eq!(total_steps(&sixty), 60);
+        assert_eq!(total_steps(&uneven), 60);
+    }
+
+    #[test]
+    fn leftover_time_carries_over() {
+        let mut timestep = FixedTimestep::default();
+        assert_eq!(timestep.advance(FIXED_TIMESTEP / 2), 0);
+        assert!((timestep.interpolation() - 0.5).abs() < 0.001);
+        assert_eq!(timestep.advance(FIXED_TIMESTEP / 2), 1);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let mut timestep = FixedTimestep::default();
+        assert_eq!(timestep.advance(FIXED_TIMESTEP * 100), MAX_FIXED_STEPS_PER_FRAME);
+        assert_eq!(timestep.interpolation(), 0.0);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod tooltip;
-mod popup;
\ No newline at end of file
+pub mod widgets;
+pub mod popup;
\ No newline at end of file
@@ -1,17 +1,154 @@
+use std::collections::VecDeque;
+use macroquad::color::Color;
+use macroquad::math::{vec2, Vec2};
+use macroquad::text::measure_text;
+use crate::assets::GlobalAssets;
+use crate::util::{draw_ansi_text_faded, draw_rounded_rect, remove_ansii_escape_codes};
 
+const FADE_IN: f32 = 0.25;
+const FADE_OUT: f32 = 0.5;
+/// How long a popup stays fully visible unless it asks for longer
+pub const DEFAULT_DURATION: f32 = 3.0;
+// popups past this many in one spot wait for the others to go
+const MAX_VISIBLE: usize = 4;
+const MARGIN: f32 = 12.0;
+const GAP: f32 = 6.0;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PopupLocation {
     Top, Bottom, BottomLeft, BottomRight,
 }
 
+/// A short notification, with ANSI colored title and lines like a tooltip
+#[derive(Clone, Debug)]
 pub struct PopupCard {
     pub title: String,
     pub lines: Vec<String>,
     pub location: PopupLocation,
+    /// seconds it stays fully visible, not counting the fades
+    pub duration: f32,
 }
 
 impl PopupCard {
     pub fn new(title: String, lines: Vec<String>, location: PopupLocation) -> Self {
-        Self { title, lines, location }
+        Self { title, lines, location, duration: DEFAULT_DURATION }
     }
-}
\ No newline at end of file
+
+    fn font_sizes(ui_scale: f32) -> (u16, u16) {
+        let title = (11.0 * ui_scale).round();
+        (title as u16, (title * 0.8).round() as u16)
+    }
+
+    fn size(&self, assets: &GlobalAssets, ui_scale: f32) -> Vec2 {
+        let (title_size, line_size) = Self::font_sizes(ui_scale);
+        let padding = 6.0 * ui_scale;
+        let line_spacing = 2.0 * ui_scale;
+
+        let title = measure_text(&remove_ansii_escape_codes(&self.title), Some(&assets.font), title_size, 1.0);
+        let mut size = vec2(title.width, title.height);
+        for line in &self.lines {
+            let measured = measure_text(&remove_ansii_escape_codes(line), Some(&assets.font), line_size, 1.0);
+            size.x = size.x.max(measured.width);
+            size.y += measured.height + line_spacing;
+        }
+        size + vec2(padding, padding) * 2.0
+    }
+
+    fn draw(&self, pos: Vec2, alpha: f32, assets: &GlobalAssets, ui_scale: f32) {
+        let (title_size, line_size) = Self::font_sizes(ui_scale);
+        let padding = 6.0 * ui_scale;
+        let line_spacing = 2.0 * ui_scale;
+
+        draw_rounded_rect(pos, self.size(assets, ui_scale), 2.0,
+                          Color::from_rgba(0, 0, 0, (180.0 * alpha) as u8),
+                          true,
+                          Some(Color::new(0.0, 0.0, 0.0, alpha)));
+
+        let title = measure_text(&remove_ansii_escape_codes(&self.title), Some(&assets.font), title_size, 1.0);
+        let mut draw_pos = vec2(pos.x + padding, pos.y + padding + title.height);
+        draw_ansi_text_faded(&self.title, draw_pos, assets, title_size, 4.0, alpha);
+        for line in &self.lines {
+            let measured = measure_text(&remove_ansii_escape_codes(line), Some(&assets.font), line_size, 1.0);
+            draw_pos.y += measured.height + line_spacing;
+            draw_ansi_text_faded(line, draw_pos, assets, line_size, 4.0, alpha);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ActivePopup {
+    card: PopupCard,
+    age: f32,
+}
+
+impl ActivePopup {
+    fn lifetime(&self) -> f32 {
+        FADE_IN + self.card.duration + FADE_OUT
+    }
+
+    fn alpha(&self) -> f32 {
+        let fade_in = self.age / FADE_IN;
+        let fade_out = (self.lifetime() - self.age) / FADE_OUT;
+        fade_in.min(fade_out).clamp(0.0, 1.0)
+    }
+}
+
+/// Popups on screen and the ones waiting for room. New popups go nearest their anchor
+/// and push the older ones further in.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    showing: Vec<ActivePopup>,
+    queued: VecDeque<PopupCard>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, card: PopupCard) {
+        self.queued.push_back(card);
+    }
+
+    /// Age the popups in real time, so they keep going while the game is paused
+    pub fn update(&mut self, delta_seconds: f32) {
+        for popup in &mut self.showing {
+            popup.age += delta_seconds;
+        }
+        self.showing.retain(|p| p.age < p.lifetime());
+
+        let mut waiting = VecDeque::new();
+        while let Some(card) = self.queued.pop_front() {
+            let at_location = self.showing.iter().filter(|p| p.card.location == card.location).count();
+            if at_location < MAX_VISIBLE {
+                self.showing.push(ActivePopup { card, age: 0.0 });
+            } else {
+                waiting.push_back(card);
+            }
+        }
+        self.queued = waiting;
+    }
+
+    pub fn draw(&self, assets: &GlobalAssets, ui_scale: f32, screen: Vec2) {
+        let margin = MARGIN * ui_scale;
+        let gap = GAP * ui_scale;
+        for location in [PopupLocation::Top, PopupLocation::Bottom, PopupLocation::BottomLeft, PopupLocation::BottomRight] {
+            let mut offset = 0.0;
+            // newest first, closest to the edge
+            for popup in self.showing.iter().rev().filter(|p| p.card.location == location) {
+                let size = popup.card.size(assets, ui_scale);
+                let x = match location {
+                    PopupLocation::Top | PopupLocation::Bottom => (screen.x - size.x) / 2.0,
+                    PopupLocation::BottomLeft => margin,
+                    PopupLocation::BottomRight => screen.x - margin - size.x,
+                };
+                let y = match location {
+                    PopupLocation::Top => margin + offset,
+                    _ => screen.y - margin - size.y - offset,
+                };
+                let alpha = popup.alpha();
+                popup.card.draw(vec2(x, y), alpha, assets, ui_scale);
+
+                // popups on their way out give up their space gradually so the rest slide over
+                let leaving = popup.age > FADE_IN;
+                offset += (size.y + gap) * if leaving { alpha } else { 1.0 };
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use macroquad::input::mouse_position;
 use macroquad::prelude::*;
 use crate::assets::GlobalAssets;
+use crate::backend::Backend;
 use crate::util::{draw_ansi_text, draw_rounded_rect, remove_ansii_escape_codes};
 
 #[derive(Clone, Debug)]
@@ -26,15 +26,15 @@ impl ToolTipCard {
     }
 }
 
-pub fn tooltip(card: ToolTipCard, global_assets: &GlobalAssets) {
-    let mouse_pos = mouse_position();
+pub fn tooltip(card: ToolTipCard, global_assets: &GlobalAssets, ui_scale: f32, backend: &dyn Backend) {
+    let mouse_pos = backend.mouse_position();
     let title_text = card.title;
     let line_texts: Vec<String> = card.lines;
 
-    let title_font_size = 11.0;
-    let line_font_size = title_font_size * 0.8;
-    let padding = 4.0;
-    let line_spacing = 2.0;
+    let title_font_size = (11.0 * ui_scale).round();
+    let line_font_size = (title_font_size * 0.8).round();
+    let padding = 4.0 * ui_scale;
+    let line_spacing = 2.0 * ui_scale;
 
     // Measure title
     let raw_title = remove_ansii_escape_codes(&title_text);
@@ -61,11 +61,11 @@ pub fn tooltip(card: ToolTipCard, global_assets: &GlobalAssets) {
 
     // Calculate size and determine position (flip left if needed)
     let bg_size = vec2(max_width + padding * 2.0, total_height + padding * 2.0);
-    let mut bg_pos = vec2(mouse_pos.0 + 12.0, mouse_pos.1 - 30.0);
+    let mut bg_pos = vec2(mouse_pos.x + 12.0, mouse_pos.y - 30.0);
 
     // Flip left if going off-screen
-    if bg_pos.x + bg_size.x > screen_width() {
-        bg_pos.x = mouse_pos.0 - bg_size.x - 12.0;
+    if bg_pos.x + bg_size.x > backend.screen_size().x {
+        bg_pos.x = mouse_pos.x - bg_size.x - 12.0;
     }
 
     // Clamp to left edge if still going off-screen
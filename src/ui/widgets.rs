@@ -0,0 +1,637 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use macroquad::color::{Color, BLACK, WHITE};
+use macroquad::input::KeyCode;
+use macroquad::math::{vec2, Rect, Vec2};
+use macroquad::text::{draw_text_ex, measure_text, TextParams};
+use crate::assets::GlobalAssets;
+use crate::controls::Action;
+use crate::gamedata::GameData;
+use crate::util::draw_rounded_rect;
+
+const ROW_HEIGHT: f32 = 28.0;
+const ROW_SPACING: f32 = 4.0;
+const PADDING: f32 = 12.0;
+const FONT_SIZE: f32 = 10.0;
+// sliders, dropdowns and text boxes sit in this much of the right side of their row
+const CONTROL_WIDTH: f32 = 160.0;
+// how long a button stays darkened after being pressed
+const PRESS_FLASH: f32 = 0.12;
+
+pub const BUTTON_FILL: Color = Color::new(69.0 / 255.0, 62.0 / 255.0, 61.0 / 255.0, 1.0);
+pub const HIGHLIGHT: Color = Color::new(222.0 / 255.0, 159.0 / 255.0, 71.0 / 255.0, 1.0);
+const PANEL_FILL: Color = Color::new(0.0, 0.0, 0.0, 200.0 / 255.0);
+const ROW_HOVER: Color = Color::new(1.0, 1.0, 1.0, 20.0 / 255.0);
+const PRESSED: Color = Color::new(170.0 / 255.0, 116.0 / 255.0, 44.0 / 255.0, 1.0);
+const DISABLED_TEXT: Color = Color::new(1.0, 1.0, 1.0, 90.0 / 255.0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Debug)]
+enum DrawCommand {
+    Rect { rect: Rect, radius: f32, fill: Color, border: Option<Color> },
+    Text { text: String, rect: Rect, align: Align, font_size: u16, color: Color },
+}
+
+impl DrawCommand {
+    fn draw(&self, assets: &GlobalAssets) {
+        match self {
+            DrawCommand::Rect { rect, radius, fill, border } => {
+                draw_rounded_rect(rect.point(), rect.size(), *radius, *fill, border.is_some(), *border);
+            }
+            DrawCommand::Text { text, rect, align, font_size, color } => {
+                let size = measure_text(text, Some(&assets.font), *font_size, 1.0);
+                let x = match align {
+                    Align::Left => rect.x,
+                    Align::Center => rect.x + (rect.w - size.width) / 2.0,
+                    Align::Right => rect.x + rect.w - size.width,
+                };
+                draw_text_ex(text, x, rect.y + (rect.h + size.height) / 2.0, TextParams {
+                    font: Some(&assets.font),
+                    font_size: *font_size,
+                    color: *color,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+}
+
+/// What the widgets respond to this frame
+pub struct UiInput {
+    pub actions: Vec<Action>,
+    pub mouse: Vec2,
+    pub screen: Vec2,
+    pub wheel: f32,
+    pub typed: Vec<char>,
+    // read straight from the keyboard, whatever back is bound to
+    pub backspace: bool,
+}
+
+impl UiInput {
+    /// Gather the rest of the frame's input to go with the actions the overlay already took
+    pub fn new(actions: Vec<Action>, data: &mut GameData) -> Self {
+        Self {
+            actions,
+            mouse: data.backend.mouse_position(),
+            screen: data.backend.screen_size(),
+            wheel: data.backend.mouse_wheel(),
+            typed: data.backend.typed_chars(),
+            backspace: data.backend.is_key_pressed(KeyCode::Backspace),
+        }
+    }
+}
+
+/// Everything the widgets need to remember between frames, kept by whatever owns the menu.
+/// Widgets are built during `update`, which leaves behind a list of shapes for `draw` to replay.
+#[derive(Clone, Debug, Default)]
+pub struct UiState {
+    // widgets are identified by the order they're built in, which is also the focus order
+    focused: usize,
+    // the dropdown that is open, the option highlighted in it and where its list was drawn
+    open: Option<usize>,
+    open_highlight: usize,
+    popup_rect: Option<Rect>,
+    // the text box being typed into
+    editing: Option<usize>,
+    // how many rows each scroll list is scrolled down
+    scroll: HashMap<usize, usize>,
+    pressed: Option<(usize, f32)>,
+    commands: Vec<DrawCommand>,
+    // drawn after everything else, for dropdown lists
+    popup_commands: Vec<DrawCommand>,
+}
+
+impl UiState {
+    /// Whether a widget is holding onto input, so back shouldn't close the menu
+    pub fn is_capturing(&self) -> bool {
+        self.open.is_some() || self.editing.is_some()
+    }
+
+    pub fn draw(&self, assets: &GlobalAssets) {
+        for command in self.commands.iter().chain(&self.popup_commands) {
+            command.draw(assets);
+        }
+    }
+}
+
+/// Lays out and runs widgets for one frame. Each widget takes the next row, top to bottom,
+/// and reports whether it was used. Call `end` once every widget is built.
+pub struct Ui<'a> {
+    state: &'a mut UiState,
+    input: UiInput,
+    ui_scale: f32,
+    // where the next row goes and how wide it is
+    cursor: Vec2,
+    width: f32,
+    // the next widgets share a row, (placed so far, how many)
+    columns: Option<(usize, usize)>,
+    // the row and column of the last space handed out, and of every widget by id, for moving focus between rows
+    row: usize,
+    cell: (usize, usize),
+    cells: Vec<(usize, usize)>,
+    next_id: usize,
+    on_top: bool,
+}
+
+impl<'a> Ui<'a> {
+    pub fn begin(state: &'a mut UiState, input: UiInput, ui_scale: f32, delta_time: f32) -> Self {
+        state.commands.clear();
+        state.popup_commands.clear();
+        if let Some((id, time)) = state.pressed {
+            state.pressed = Some((id, time - delta_time)).filter(|(_, t)| *t > 0.0);
+        }
+
+        let mut ui = Self {
+            state,
+            ui_scale,
+            cursor: vec2(PADDING * ui_scale, PADDING * ui_scale),
+            width: input.screen.x - PADDING * ui_scale * 2.0,
+            input,
+            columns: None,
+            row: 0,
+            cell: (0, 0),
+            cells: Vec::new(),
+            next_id: 0,
+            on_top: false,
+        };
+
+        // clicking away from an open dropdown closes it without pressing whatever was underneath
+        let mouse = ui.input.mouse;
+        if ui.state.open.is_some() && !ui.state.popup_rect.is_some_and(|r| r.contains(mouse)) && ui.consume(Action::UIClick) {
+            ui.state.open = None;
+        }
+        ui
+    }
+
+    /// Finish the frame, moving focus with whatever navigation the widgets didn't use.
+    /// Up and down move to the nearest column of the row above or below. Returns the actions that are left over.
+    pub fn end(self) -> Vec<Action> {
+        let count = self.next_id;
+        if self.state.open.is_none() {
+            self.state.popup_rect = None;
+        }
+        self.state.focused = self.state.focused.min(count.saturating_sub(1));
+
+        let capturing = self.state.is_capturing();
+        let mut leftover = Vec::new();
+        for action in self.input.actions {
+            match action {
+                Action::UIUp if !capturing && count > 0 => self.state.focused = Self::row_step(&self.cells, self.state.focused, false),
+                Action::UIDown if !capturing && count > 0 => self.state.focused = Self::row_step(&self.cells, self.state.focused, true),
+                _ => leftover.push(action),
+            }
+        }
+        leftover
+    }
+
+    // the widget in the next or previous row with widgets, wrapping around, closest to the same column
+    fn row_step(cells: &[(usize, usize)], focused: usize, down: bool) -> usize {
+        let (row, column) = cells[focused];
+        let mut rows = cells.iter().map(|(r, _)| *r).collect::<Vec<usize>>();
+        rows.dedup();
+        let index = rows.iter().position(|r| *r == row).unwrap_or(0);
+        let target = if down { rows[(index + 1) % rows.len()] } else { rows[(index + rows.len() - 1) % rows.len()] };
+        (0..cells.len())
+            .filter(|id| cells[*id].0 == target)
+            .min_by_key(|id| cells[*id].1.abs_diff(column))
+            .unwrap_or(focused)
+    }
+
+    /// Draw a panel in the middle of the screen with a title, and put the following widgets inside it
+    pub fn panel(&mut self, size: Vec2, title: &str) {
+        let size = size * self.ui_scale;
+        let rect = Rect::new((self.input.screen.x - size.x) / 2.0, (self.input.screen.y - size.y) / 2.0, size.x, size.y);
+        self.draw_rect(rect, 4.0, PANEL_FILL, Some(HIGHLIGHT));
+
+        let padding = PADDING * self.ui_scale;
+        let title_rect = Rect::new(rect.x, rect.y + padding, rect.w, ROW_HEIGHT * self.ui_scale);
+        self.draw_text(title, title_rect, Align::Center, HIGHLIGHT);
+
+        self.cursor = vec2(rect.x + padding, title_rect.y + title_rect.h);
+        self.width = rect.w - padding * 2.0;
+        self.columns = None;
+    }
+
+    /// Put the next `count` widgets side by side in one row
+    pub fn columns(&mut self, count: usize) {
+        self.columns = Some((0, count.max(1)));
+    }
+
+    /// Leave a gap before the next row
+    pub fn space(&mut self, height: f32) {
+        self.cursor.y += height * self.ui_scale;
+    }
+
+    pub fn label(&mut self, text: &str) {
+        self.label_colored(text, WHITE);
+    }
+
+    pub fn label_colored(&mut self, text: &str, color: Color) {
+        let rect = self.allocate(ROW_HEIGHT);
+        self.draw_text(text, rect, Align::Left, color);
+    }
+
+    /// Returns true the frame it's pressed
+    pub fn button(&mut self, label: &str) -> bool {
+        self.button_styled(label, true, WHITE)
+    }
+
+    /// A button that is greyed out and can't be pressed while `enabled` is false
+    pub fn button_enabled(&mut self, label: &str, enabled: bool) -> bool {
+        self.button_styled(label, enabled, WHITE)
+    }
+
+    /// A button with its label in `color`, for values that need to stand out
+    pub fn button_colored(&mut self, label: &str, color: Color) -> bool {
+        self.button_styled(label, true, color)
+    }
+
+    fn button_styled(&mut self, label: &str, enabled: bool, color: Color) -> bool {
+        let rect = self.allocate(ROW_HEIGHT);
+        let (id, focused) = self.next_widget();
+
+        // buttons in a row are moved between with left and right
+        let (row, column) = self.cell;
+        if focused {
+            let beside = |id: usize| self.cells.get(id).is_some_and(|(r, _)| *r == row);
+            if column > 0 && id > 0 && beside(id - 1) && self.consume(Action::UILeft) {
+                self.state.focused = id - 1;
+            } else if self.columns.is_some() && self.consume(Action::UIRight) {
+                // the widget to the right hasn't been built yet, it gets focus if it lands in this row
+                self.state.focused = id + 1;
+            }
+        }
+
+        let pressed = (self.clicked(id, rect) || self.focused_action(focused, Action::UIConfirm)) && enabled;
+        if pressed {
+            self.state.pressed = Some((id, PRESS_FLASH));
+        }
+
+        let fill = if self.state.pressed.is_some_and(|(p, _)| p == id) {
+            PRESSED
+        } else if enabled && (focused || self.hovered(rect)) {
+            HIGHLIGHT
+        } else {
+            BUTTON_FILL
+        };
+        self.draw_rect(rect, 2.0, fill, Some(BLACK));
+        self.draw_text(label, rect, Align::Center, if enabled { color } else { DISABLED_TEXT });
+        pressed
+    }
+
+    /// An on / off switch, flipped by clicking anywhere on its row. Returns true when it changes.
+    pub fn toggle(&mut self, label: &str, value: &mut bool) -> bool {
+        let rect = self.allocate(ROW_HEIGHT);
+        let (id, focused) = self.next_widget();
+
+        let changed = self.clicked(id, rect)
+            || self.focused_action(focused, Action::UIConfirm)
+            || self.focused_action(focused, Action::UILeft)
+            || self.focused_action(focused, Action::UIRight);
+        if changed {
+            *value = !*value;
+        }
+
+        self.draw_row(rect, label, focused);
+        let s = self.ui_scale;
+        let size = 14.0 * s;
+        let check = Rect::new(rect.x + rect.w - size - 6.0 * s, rect.y + (rect.h - size) / 2.0, size, size);
+        self.draw_rect(check, 2.0, if *value { HIGHLIGHT } else { BUTTON_FILL }, Some(BLACK));
+        let text = Rect::new(check.x - 60.0 * s, rect.y, 52.0 * s, rect.h);
+        self.draw_text(if *value { "On" } else { "Off" }, text, Align::Right, WHITE);
+        changed
+    }
+
+    /// Pick a value by clicking along the track or stepping with left and right.
+    /// The value snaps to multiples of `step` from the start of the range. Returns true when it changes.
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>, step: f32, format: impl Fn(f32) -> String) -> bool {
+        let (min, max) = (*range.start(), *range.end());
+        let rect = self.allocate(ROW_HEIGHT);
+        let (id, focused) = self.next_widget();
+        let s = self.ui_scale;
+        let control = self.control_rect(rect);
+        let track = Rect::new(control.x + 6.0 * s, rect.y + rect.h / 2.0 - 3.0 * s, control.w - 12.0 * s, 6.0 * s);
+
+        let old = *value;
+        let mut moved = false;
+        if self.clicked(id, rect) && self.input.mouse.x >= control.x {
+            *value = min + ((self.input.mouse.x - track.x) / track.w).clamp(0.0, 1.0) * (max - min);
+            moved = true;
+        }
+        if self.focused_action(focused, Action::UILeft) {
+            *value -= step;
+            moved = true;
+        }
+        if self.focused_action(focused, Action::UIRight) {
+            *value += step;
+            moved = true;
+        }
+        // values that came from elsewhere are left alone until the slider is used
+        if moved && step > 0.0 {
+            *value = min + ((*value - min) / step).round() * step;
+        }
+        if moved {
+            *value = value.clamp(min, max);
+        }
+
+        self.draw_row(rect, label, focused);
+        let text = Rect::new(control.x - 70.0 * s, rect.y, 64.0 * s, rect.h);
+        self.draw_text(&format(*value), text, Align::Right, WHITE);
+
+        let amount = if max > min { (*value - min) / (max - min) } else { 0.0 };
+        self.draw_rect(track, 2.0, BUTTON_FILL, Some(BLACK));
+        if amount > 0.0 {
+            self.draw_rect(Rect::new(track.x, track.y, track.w * amount, track.h), 2.0, HIGHLIGHT, None);
+        }
+        let knob = vec2(6.0, 14.0) * s;
+        let knob = Rect::new(track.x + track.w * amount - knob.x / 2.0, rect.y + (rect.h - knob.y) / 2.0, knob.x, knob.y);
+        self.draw_rect(knob, 2.0, if focused || self.hovered(rect) { HIGHLIGHT } else { WHITE }, Some(BLACK));
+
+        *value != old
+    }
+
+    /// Choose one of `options` from a list that opens below it.
+    /// Left and right cycle through the options without opening the list. Returns true when the choice changes.
+    pub fn dropdown(&mut self, label: &str, options: &[&str], selected: &mut usize) -> bool {
+        let rect = self.allocate(ROW_HEIGHT);
+        let (id, focused) = self.next_widget();
+        let control = self.control_rect(rect);
+        self.draw_row(rect, label, focused);
+        if options.is_empty() {
+            return false;
+        }
+        *selected = (*selected).min(options.len() - 1);
+        let old = *selected;
+
+        if self.state.open == Some(id) {
+            let count = options.len();
+            let option_height = ROW_HEIGHT * self.ui_scale;
+            let list = Rect::new(control.x, rect.y + rect.h, control.w, option_height * count as f32);
+            self.state.popup_rect = Some(list);
+
+            if self.consume(Action::UIUp) {
+                self.state.open_highlight = (self.state.open_highlight + count - 1) % count;
+            }
+            if self.consume(Action::UIDown) {
+                self.state.open_highlight = (self.state.open_highlight + 1) % count;
+            }
+            let mouse = self.input.mouse;
+            if list.contains(mouse) {
+                self.state.open_highlight = (((mouse.y - list.y) / option_height) as usize).min(count - 1);
+            }
+            if (list.contains(mouse) && self.consume(Action::UIClick)) || self.consume(Action::UIConfirm) {
+                *selected = self.state.open_highlight;
+                self.state.open = None;
+            } else if self.consume(Action::UIBack) || self.consume(Action::Pause) {
+                self.state.open = None;
+            }
+
+            if self.state.open.is_some() {
+                self.on_top = true;
+                self.draw_rect(list, 2.0, BUTTON_FILL, Some(HIGHLIGHT));
+                for (i, option) in options.iter().enumerate() {
+                    let row = Rect::new(list.x, list.y + option_height * i as f32, list.w, option_height);
+                    if i == self.state.open_highlight {
+                        self.draw_rect(row, 2.0, HIGHLIGHT, None);
+                    }
+                    self.draw_text(option, Self::inset(row, 6.0 * self.ui_scale), Align::Left, WHITE);
+                }
+                self.on_top = false;
+            }
+        } else if self.clicked(id, rect) || self.focused_action(focused, Action::UIConfirm) {
+            self.state.open = Some(id);
+            self.state.open_highlight = *selected;
+        } else if self.focused_action(focused, Action::UILeft) {
+            *selected = (*selected + options.len() - 1) % options.len();
+        } else if self.focused_action(focused, Action::UIRight) {
+            *selected = (*selected + 1) % options.len();
+        }
+
+        let border = if self.state.open == Some(id) { HIGHLIGHT } else { BLACK };
+        self.draw_rect(control, 2.0, BUTTON_FILL, Some(border));
+        let inner = Self::inset(control, 6.0 * self.ui_scale);
+        self.draw_text(options[*selected], inner, Align::Left, WHITE);
+        self.draw_text("v", inner, Align::Right, WHITE);
+        *selected != old
+    }
+
+    /// A text box, typed into after it's clicked or confirmed. Backspace deletes and confirm finishes.
+    /// Returns true whenever the text changes.
+    pub fn text_input(&mut self, label: &str, text: &mut String, max_len: usize) -> bool {
+        let rect = self.allocate(ROW_HEIGHT);
+        let (id, focused) = self.next_widget();
+        let control = self.control_rect(rect);
+
+        let mut changed = false;
+        if self.state.editing == Some(id) {
+            for c in std::mem::take(&mut self.input.typed) {
+                if !c.is_control() && text.chars().count() < max_len {
+                    text.push(c);
+                    changed = true;
+                }
+            }
+            if self.input.backspace {
+                changed |= text.pop().is_some();
+            }
+            // back is backspace by default, so it only stops typing when it comes from another key
+            let back = self.consume(Action::UIBack) && !self.input.backspace;
+            let clicked_away = self.input.actions.contains(&Action::UIClick) && !rect.contains(self.input.mouse);
+            if !focused || clicked_away || back || self.consume(Action::UIConfirm) || self.consume(Action::Pause) {
+                self.state.editing = None;
+            }
+        } else if self.clicked(id, rect) || self.focused_action(focused, Action::UIConfirm) {
+            self.state.editing = Some(id);
+            // anything typed before the box was opened doesn't count
+            self.input.typed.clear();
+        }
+
+        self.draw_row(rect, label, focused);
+        let editing = self.state.editing == Some(id);
+        self.draw_rect(control, 2.0, BUTTON_FILL, Some(if editing { HIGHLIGHT } else { BLACK }));
+        let shown = if editing { format!("{}_", text) } else { text.clone() };
+        self.draw_text(&shown, Self::inset(control, 6.0 * self.ui_scale), Align::Left, WHITE);
+        changed
+    }
+
+    /// A list showing `visible_rows` items at a time, scrolled with the mouse wheel or by moving the selection.
+    /// Returns the index of the item that was clicked or confirmed.
+    pub fn scroll_list(&mut self, items: &[String], selected: &mut usize, visible_rows: usize) -> Option<usize> {
+        let row_height = ROW_HEIGHT * self.ui_scale;
+        let rect = self.allocate(ROW_HEIGHT * visible_rows as f32);
+        let (id, focused) = self.next_widget();
+        *selected = (*selected).min(items.len().saturating_sub(1));
+
+        let max_scroll = items.len().saturating_sub(visible_rows);
+        let mut scroll = self.state.scroll.get(&id).copied().unwrap_or(0);
+        if self.hovered(rect) && self.input.wheel != 0.0 {
+            scroll = if self.input.wheel > 0.0 { scroll.saturating_sub(1) } else { scroll + 1 };
+            self.input.wheel = 0.0;
+        }
+
+        let mut activated = None;
+        if focused && !items.is_empty() {
+            // at either end up and down move focus out of the list instead
+            if *selected > 0 && self.consume(Action::UIUp) {
+                *selected -= 1;
+                scroll = scroll.min(*selected);
+            } else if *selected + 1 < items.len() && self.consume(Action::UIDown) {
+                *selected += 1;
+                scroll = scroll.max((*selected + 1).saturating_sub(visible_rows));
+            }
+            if self.consume(Action::UIConfirm) {
+                activated = Some(*selected);
+            }
+        }
+        let mouse = self.input.mouse;
+        if self.clicked(id, rect) {
+            let index = scroll + ((mouse.y - rect.y) / row_height) as usize;
+            if index < items.len() {
+                *selected = index;
+                activated = Some(index);
+            }
+        }
+        scroll = scroll.min(max_scroll);
+        self.state.scroll.insert(id, scroll);
+
+        let s = self.ui_scale;
+        self.draw_rect(rect, 2.0, Color::new(0.0, 0.0, 0.0, 0.3), Some(if focused { HIGHLIGHT } else { BLACK }));
+        let bar_width = if max_scroll > 0 { 8.0 * s } else { 0.0 };
+        for (i, item) in items.iter().enumerate().skip(scroll).take(visible_rows) {
+            let row = Rect::new(rect.x + 2.0 * s, rect.y + row_height * (i - scroll) as f32 + 2.0 * s, rect.w - 4.0 * s - bar_width, row_height - 4.0 * s);
+            if i == *selected {
+                self.draw_rect(row, 2.0, HIGHLIGHT, None);
+            } else if self.hovered(row) {
+                self.draw_rect(row, 2.0, ROW_HOVER, None);
+            }
+            self.draw_text(item, Self::inset(row, 6.0 * s), Align::Left, WHITE);
+        }
+
+        if max_scroll > 0 {
+            let track = Rect::new(rect.x + rect.w - bar_width, rect.y + 2.0 * s, bar_width - 2.0 * s, rect.h - 4.0 * s);
+            let thumb_height = track.h * visible_rows as f32 / items.len() as f32;
+            let thumb_y = track.y + (track.h - thumb_height) * scroll as f32 / max_scroll as f32;
+            self.draw_rect(Rect::new(track.x, thumb_y, track.w, thumb_height), 2.0, BUTTON_FILL, None);
+        }
+        activated
+    }
+
+    // the space for the next widget, `height` tall before scaling
+    fn allocate(&mut self, height: f32) -> Rect {
+        let height = height * self.ui_scale;
+        let spacing = ROW_SPACING * self.ui_scale;
+        let Some((placed, count)) = self.columns else {
+            let rect = Rect::new(self.cursor.x, self.cursor.y, self.width, height);
+            self.cursor.y += height + spacing;
+            self.cell = (self.row, 0);
+            self.row += 1;
+            return rect;
+        };
+
+        let width = (self.width - spacing * (count - 1) as f32) / count as f32;
+        let rect = Rect::new(self.cursor.x + (width + spacing) * placed as f32, self.cursor.y, width, height);
+        self.cell = (self.row, placed);
+        if placed + 1 == count {
+            self.columns = None;
+            self.cursor.y += height + spacing;
+            self.row += 1;
+        } else {
+            self.columns = Some((placed + 1, count));
+        }
+        rect
+    }
+
+    // the id of the next widget and whether it has focus, placed in the space just allocated
+    fn next_widget(&mut self) -> (usize, bool) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.cells.push(self.cell);
+        (id, self.state.focused == id)
+    }
+
+    fn hovered(&self, rect: Rect) -> bool {
+        let mouse = self.input.mouse;
+        // the open dropdown's list covers whatever is under it
+        rect.contains(mouse) && !(self.state.open.is_some() && self.state.popup_rect.is_some_and(|r| r.contains(mouse)))
+    }
+
+    // clicking a widget also focuses it
+    fn clicked(&mut self, id: usize, rect: Rect) -> bool {
+        if self.hovered(rect) && self.consume(Action::UIClick) {
+            self.state.focused = id;
+            return true;
+        }
+        false
+    }
+
+    fn focused_action(&mut self, focused: bool, action: Action) -> bool {
+        focused && self.consume(action)
+    }
+
+    fn consume(&mut self, action: Action) -> bool {
+        match self.input.actions.iter().position(|a| *a == action) {
+            Some(i) => {
+                self.input.actions.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn control_rect(&self, row: Rect) -> Rect {
+        let width = (CONTROL_WIDTH * self.ui_scale).min(row.w / 2.0);
+        let inset = 4.0 * self.ui_scale;
+        Rect::new(row.x + row.w - width, row.y + inset, width, row.h - inset * 2.0)
+    }
+
+    fn inset(rect: Rect, amount: f32) -> Rect {
+        Rect::new(rect.x + amount, rect.y, rect.w - amount * 2.0, rect.h)
+    }
+
+    // the hover background and label shared by the labelled widgets
+    fn draw_row(&mut self, rect: Rect, label: &str, focused: bool) {
+        if focused || self.hovered(rect) {
+            self.draw_rect(rect, 2.0, ROW_HOVER, None);
+        }
+        self.draw_text(label, Self::inset(rect, 6.0 * self.ui_scale), Align::Left, WHITE);
+    }
+
+    fn draw_rect(&mut self, rect: Rect, radius: f32, fill: Color, border: Option<Color>) {
+        self.push(DrawCommand::Rect { rect, radius, fill, border });
+    }
+
+    fn draw_text(&mut self, text: &str, rect: Rect, align: Align, color: Color) {
+        let font_size = (FONT_SIZE * self.ui_scale).round() as u16;
+        self.push(DrawCommand::Text { text: text.to_string(), rect, align, font_size, color });
+    }
+
+    fn push(&mut self, command: DrawCommand) {
+        if self.on_top {
+            self.state.popup_commands.push(command);
+        } else {
+            self.state.commands.push(command);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ui;
+
+    #[test]
+    fn up_and_down_keep_the_column_in_a_grid() {
+        // two rows of a label and three buttons, then a row of two buttons
+        let cells = [(0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 0), (2, 1)];
+        assert_eq!(Ui::row_step(&cells, 1, true), 4);
+        assert_eq!(Ui::row_step(&cells, 4, false), 1);
+        // the nearest column when the next row is narrower, and wrapping past either end
+        assert_eq!(Ui::row_step(&cells, 5, true), 7);
+        assert_eq!(Ui::row_step(&cells, 7, true), 0);
+        assert_eq!(Ui::row_step(&cells, 0, false), 7);
+    }
+}
@@ -1,10 +1,13 @@
 use macroquad::prelude::*;
 use crate::animation::Animator;
+use crate::backend::Backend;
+use crate::camera::{Camera, BASE_SPRITE_SIZE};
 use crate::error::GameError;
 use crate::gamedata::GameData;
 use crate::gamestate::GameStateAction;
-use crate::util::{get_sprite_scale, scale_position};
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum InteractableAttribute {
     Int(i32),
     UInt(u32),
@@ -15,6 +18,8 @@ pub enum InteractableAttribute {
 
 pub trait Interactable {
     fn interact(&mut self) -> Result<GameStateAction, GameError>;
+    /// What kind of interactable this is, used to rebuild it from a save
+    fn get_type(&self) -> &'static str;
     fn get_name(&self) -> String;
     fn get_sprite_size(&self) -> Vec2;
     fn get_animator(&self) -> &Animator;
@@ -27,15 +32,26 @@ pub trait Interactable {
     fn distance_from_player(&self, data: &GameData) -> f32;
     fn get_attribute(&self, attribute: &str) -> Option<InteractableAttribute>;
     fn set_attribute(&mut self, attribute: &str, value: InteractableAttribute) -> Result<(), String>;
+    /// Extra lines for the tooltip shown when hovering it
+    fn tooltip_lines(&self, _data: &GameData) -> Vec<String> {
+        Vec::new()
+    }
+    /// The attributes that make up this interactable's state, these are what gets saved
+    fn attribute_names(&self) -> &'static [&'static str];
+
+    /// Where the sprite meets the ground, things with a higher depth are drawn in front
+    fn depth(&self) -> f32 {
+        self.get_pos().y + BASE_SPRITE_SIZE
+    }
 
-    fn get_scaled_pos(&self) -> Vec2 {
-        scale_position(self.get_pos())
+    fn get_screen_pos(&self, camera: &Camera, screen_size: Vec2) -> Vec2 {
+        camera.world_to_screen(self.get_pos(), screen_size)
     }
 
-    fn is_mouse_over(&self, _data: &GameData) -> bool {
-        let mouse_pos = vec2(mouse_position().0, mouse_position().1);
-        let sprite_scale = get_sprite_scale();
-        let pos = self.get_scaled_pos();
+    fn is_mouse_over(&self, camera: &Camera, backend: &dyn Backend) -> bool {
+        let mouse_pos = backend.mouse_position();
+        let sprite_scale = camera.sprite_scale(backend.screen_size());
+        let pos = self.get_screen_pos(camera, backend.screen_size());
         let rect = Rect::new(pos.x, pos.y, sprite_scale.x, sprite_scale.y);
         rect.contains(mouse_pos)
     }
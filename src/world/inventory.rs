@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+
+/// How many slots the player's inventory has
+pub const INVENTORY_SLOTS: usize = 24;
+/// How many of an item fit in one slot
+pub const DEFAULT_MAX_STACK: u32 = 99;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: String,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new<S: Into<String>>(item: S, count: u32) -> Self {
+        Self {
+            item: item.into(),
+            count,
+        }
+    }
+}
+
+/// A fixed number of slots, each empty or holding a stack of one item
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new(INVENTORY_SLOTS)
+    }
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: vec![None; size],
+        }
+    }
+
+    /// Add empty slots until there are at least `size`. Returns true if any were added.
+    pub fn ensure_slots(&mut self, size: usize) -> bool {
+        if self.slots.len() >= size {
+            return false;
+        }
+        self.slots.resize(size, None);
+        true
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot).and_then(|s| s.as_ref())
+    }
+
+    /// How many of an item there are across every slot
+    pub fn count(&self, item: &str) -> u32 {
+        self.slots.iter().flatten().filter(|s| s.item == item).map(|s| s.count).sum()
+    }
+
+    /// Add items, topping up stacks of the same item before starting new ones.
+    /// Returns how many didn't fit.
+    pub fn add(&mut self, item: &str, mut count: u32, max_stack: u32) -> u32 {
+        for stack in self.slots.iter_mut().flatten().filter(|s| s.item == item) {
+            let moved = count.min(max_stack.saturating_sub(stack.count));
+            stack.count += moved;
+            count -= moved;
+        }
+        for slot in self.slots.iter_mut().filter(|s| s.is_none()) {
+            if count == 0 {
+                break;
+            }
+            let moved = count.min(max_stack);
+            *slot = Some(ItemStack::new(item, moved));
+            count -= moved;
+        }
+        count
+    }
+
+    /// Remove `count` of an item from wherever they are. Nothing is removed if there aren't enough.
+    pub fn remove(&mut self, item: &str, mut count: u32) -> bool {
+        if self.count(item) < count {
+            return false;
+        }
+        // take from the last stacks first so the first ones stay put
+        for slot in self.slots.iter_mut().rev() {
+            let Some(stack) = slot.as_mut().filter(|s| s.item == item) else {
+                continue;
+            };
+            let taken = count.min(stack.count);
+            stack.count -= taken;
+            count -= taken;
+            if stack.count == 0 {
+                *slot = None;
+            }
+            if count == 0 {
+                break;
+            }
+        }
+        true
+    }
+
+    /// Empty a slot, returning what was in it
+    pub fn take(&mut self, slot: usize) -> Option<ItemStack> {
+        self.slots.get_mut(slot).and_then(|s| s.take())
+    }
+
+    /// Take the bigger half of a stack out of a slot, leaving the rest
+    pub fn split(&mut self, slot: usize) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(slot)?.as_mut()?;
+        let taken = stack.count.div_ceil(2);
+        stack.count -= taken;
+        let split = ItemStack::new(stack.item.clone(), taken);
+        if stack.count == 0 {
+            self.slots[slot] = None;
+        }
+        Some(split)
+    }
+
+    /// Put a stack into a slot. It fills an empty slot, merges into the same item up to `max_stack`,
+    /// or swaps with a different item. Returns whatever is left over or was swapped out.
+    pub fn place(&mut self, slot: usize, mut stack: ItemStack, max_stack: u32) -> Option<ItemStack> {
+        let Some(target) = self.slots.get_mut(slot) else {
+            return Some(stack);
+        };
+        match target {
+            None => {
+                *target = Some(stack);
+                None
+            }
+            Some(existing) if existing.item == stack.item => {
+                let moved = stack.count.min(max_stack.saturating_sub(existing.count));
+                existing.count += moved;
+                stack.count -= moved;
+                Some(stack).filter(|s| s.count > 0)
+            }
+            Some(_) => target.replace(stack),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(inventory: &Inventory) -> Vec<Option<(&str, u32)>> {
+        inventory.slots().iter().map(|s| s.as_ref().map(|s| (s.item.as_str(), s.count))).collect()
+    }
+
+    #[test]
+    fn add_tops_up_stacks_then_fills_empty_slots() {
+        let mut inventory = Inventory::new(3);
+        inventory.place(1, ItemStack::new("stone", 8), 10);
+
+        assert_eq!(inventory.add("stone", 15, 10), 0);
+        assert_eq!(counts(&inventory), vec![Some(("stone", 10)), Some(("stone", 10)), Some(("stone", 3))]);
+
+        // whatever is past every stack's max is handed back
+        assert_eq!(inventory.add("stone", 9, 10), 2);
+        assert_eq!(inventory.count("stone"), 30);
+        assert_eq!(inventory.add("wood", 1, 10), 1);
+    }
+
+    #[test]
+    fn remove_takes_from_the_last_stacks_first() {
+        let mut inventory = Inventory::new(3);
+        inventory.add("stone", 25, 10);
+
+        assert!(inventory.remove("stone", 7));
+        assert_eq!(counts(&inventory), vec![Some(("stone", 10)), Some(("stone", 8)), None]);
+
+        // nothing is taken when there aren't enough
+        assert!(!inventory.remove("stone", 19));
+        assert_eq!(inventory.count("stone"), 18);
+        assert!(!inventory.remove("wood", 1));
+    }
+
+    #[test]
+    fn split_takes_the_bigger_half() {
+        let mut inventory = Inventory::new(2);
+        inventory.add("stone", 7, 10);
+
+        assert_eq!(inventory.split(0), Some(ItemStack::new("stone", 4)));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("stone", 3)));
+
+        // splitting a single item takes it and empties the slot
+        inventory.place(1, ItemStack::new("wood", 1), 10);
+        assert_eq!(inventory.split(1), Some(ItemStack::new("wood", 1)));
+        assert_eq!(inventory.get(1), None);
+        assert_eq!(inventory.split(1), None);
+    }
+
+    #[test]
+    fn place_merges_up_to_max_and_swaps_other_items() {
+        let mut inventory = Inventory::new(2);
+        assert_eq!(inventory.place(0, ItemStack::new("stone", 6), 10), None);
+
+        // merging hands back what doesn't fit under the max
+        assert_eq!(inventory.place(0, ItemStack::new("stone", 7), 10), Some(ItemStack::new("stone", 3)));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("stone", 10)));
+
+        // a different item swaps with what was there
+        assert_eq!(inventory.place(0, ItemStack::new("wood", 2), 10), Some(ItemStack::new("stone", 10)));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("wood", 2)));
+
+        // slots past the end hand the stack straight back
+        assert_eq!(inventory.place(5, ItemStack::new("wood", 1), 10), Some(ItemStack::new("wood", 1)));
+    }
+}
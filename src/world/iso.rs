@@ -0,0 +1,143 @@
+use macroquad::math::{vec2, Vec2};
+
+/// How a map's tile grid is laid out in the world
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    /// square tiles in rows and columns
+    Orthogonal,
+    /// diamond tiles, grid x runs down-right and grid y runs down-left
+    Isometric,
+}
+
+/// Converts between tile grid coordinates and world units.
+/// Grid coordinates are fractional, so (0.5, 0.5) is the middle of the first tile.
+#[derive(Clone, Copy, Debug)]
+pub struct Grid {
+    pub projection: Projection,
+    /// the size of one tile in world units, for isometric maps this is the diamond's width and height
+    pub tile_size: Vec2,
+    /// how many tiles across and down
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Grid {
+    /// The size of the whole grid in world units
+    pub fn world_size(&self) -> Vec2 {
+        match self.projection {
+            Projection::Orthogonal => vec2(self.width as f32, self.height as f32) * self.tile_size,
+            // the diamond of diamonds fits in a box as wide as both sides together
+            Projection::Isometric => Vec2::splat((self.width + self.height) as f32) * self.tile_size / 2.0,
+        }
+    }
+
+    pub fn grid_to_world(&self, grid: Vec2) -> Vec2 {
+        match self.projection {
+            Projection::Orthogonal => grid * self.tile_size,
+            Projection::Isometric => vec2(
+                (grid.x - grid.y) * self.tile_size.x / 2.0 + self.origin_x(),
+                (grid.x + grid.y) * self.tile_size.y / 2.0,
+            ),
+        }
+    }
+
+    pub fn world_to_grid(&self, world: Vec2) -> Vec2 {
+        match self.projection {
+            Projection::Orthogonal => world / self.tile_size,
+            Projection::Isometric => {
+                let x = (world.x - self.origin_x()) / (self.tile_size.x / 2.0);
+                let y = world.y / (self.tile_size.y / 2.0);
+                vec2((y + x) / 2.0, (y - x) / 2.0)
+            }
+        }
+    }
+
+    /// The tile a point in the world is on, if it is on the grid at all
+    pub fn tile_at(&self, world: Vec2) -> Option<(u32, u32)> {
+        let grid = self.world_to_grid(world).floor();
+        if grid.x < 0.0 || grid.y < 0.0 || grid.x >= self.width as f32 || grid.y >= self.height as f32 {
+            return None;
+        }
+        Some((grid.x as u32, grid.y as u32))
+    }
+
+    /// The top left of the box a tile is drawn in
+    pub fn tile_origin(&self, x: u32, y: u32) -> Vec2 {
+        match self.projection {
+            Projection::Orthogonal => self.grid_to_world(vec2(x as f32, y as f32)),
+            // the top corner of the diamond is in the middle of the box
+            Projection::Isometric => self.grid_to_world(vec2(x as f32, y as f32)) - vec2(self.tile_size.x / 2.0, 0.0),
+        }
+    }
+
+    // shifts isometric maps right so tile (0, height) isn't off the left edge of the world
+    fn origin_x(&self) -> f32 {
+        self.height as f32 * self.tile_size.x / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(projection: Projection) -> Grid {
+        Grid {
+            projection,
+            tile_size: vec2(32.0, 16.0),
+            width: 4,
+            height: 3,
+        }
+    }
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn projections_round_trip() {
+        for projection in [Projection::Orthogonal, Projection::Isometric] {
+            let grid = grid(projection);
+            for point in [vec2(0.0, 0.0), vec2(0.5, 0.5), vec2(3.25, 1.75), vec2(4.0, 3.0), vec2(-1.0, 2.5)] {
+                assert_near(grid.world_to_grid(grid.grid_to_world(point)), point);
+                assert_near(grid.grid_to_world(grid.world_to_grid(point * 20.0)), point * 20.0);
+            }
+        }
+    }
+
+    #[test]
+    fn isometric_grid_is_shifted_right_by_its_height() {
+        let grid = grid(Projection::Isometric);
+        // the top corner of tile (0, 0) is three half tiles in, so the left corner of the last row is at x 0
+        assert_near(grid.grid_to_world(vec2(0.0, 0.0)), vec2(48.0, 0.0));
+        assert_near(grid.grid_to_world(vec2(0.0, 3.0)), vec2(0.0, 24.0));
+        assert_near(grid.grid_to_world(vec2(4.0, 0.0)), vec2(112.0, 32.0));
+        assert_near(grid.grid_to_world(vec2(4.0, 3.0)), vec2(64.0, 56.0));
+        assert_near(grid.world_size(), vec2(112.0, 56.0));
+        assert_near(grid.tile_origin(0, 0), vec2(32.0, 0.0));
+    }
+
+    #[test]
+    fn tile_at_finds_edge_tiles() {
+        for projection in [Projection::Orthogonal, Projection::Isometric] {
+            let grid = grid(projection);
+            for (x, y) in [(0, 0), (0, 2), (3, 0), (3, 2)] {
+                let middle = grid.grid_to_world(vec2(x as f32 + 0.5, y as f32 + 0.5));
+                assert_eq!(grid.tile_at(middle), Some((x, y)), "{:?}", projection);
+            }
+        }
+    }
+
+    #[test]
+    fn tile_at_is_none_off_the_grid() {
+        for projection in [Projection::Orthogonal, Projection::Isometric] {
+            let grid = grid(projection);
+            for point in [vec2(-0.5, 0.5), vec2(0.5, -0.5), vec2(4.5, 0.5), vec2(0.5, 3.5)] {
+                assert_eq!(grid.tile_at(grid.grid_to_world(point)), None, "{:?} {}", projection, point);
+            }
+        }
+        // the corners of an isometric map's bounding box are outside the diamond
+        let grid = grid(Projection::Isometric);
+        assert_eq!(grid.tile_at(vec2(1.0, 1.0)), None);
+        assert_eq!(grid.tile_at(vec2(111.0, 55.0)), None);
+    }
+}
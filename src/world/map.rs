@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::path::Path;
+use macroquad::math::{vec2, Rect, Vec2};
+use macroquad::texture::Texture2D;
+use macroquad_tiled::Map;
+use serde_json::Value;
+use crate::assets::GlobalAssets;
+use crate::backend::Backend;
+use crate::camera::Camera;
+use crate::world::iso::{Grid, Projection};
+use crate::world::tmx;
+
+/*
+Levels are made in Tiled (https://www.mapeditor.org/) and exported as JSON, or loaded from disk as .tmx
+(with tile layers saved as CSV). Maps can be orthogonal or isometric.
+
+- Tile layers are drawn bottom to top. Hidden layers are not drawn.
+- A tile layer named "Collision", or with a `collision` property set to true, is solid wherever it has a tile.
+- Object layers spawn things by the object's type (class in newer versions of Tiled):
+    "rock"      - a minable rock. Optional properties: `kind` (an id from assets/data/rocks.json, random if not set),
+                  `name` (otherwise named after its kind), `rotation`
+    "portal"    - a door to another scene. Properties: `scene`, and optionally `spawn` and `sprite` (a frame of the rock sheet)
+    "spawn"     - where the player starts, the first one unless a portal asks for one by name
+    "collision" - a solid rectangle
+*/
+
+/// World units per map pixel, so 16px tiles are the same size as other sprites
+pub const MAP_SCALE: f32 = 2.0;
+
+#[derive(Debug, Clone)]
+pub struct MapObject {
+    pub ty: String,
+    pub name: String,
+    /// top left corner in world units
+    pub pos: Vec2,
+    pub size: Vec2,
+    pub properties: HashMap<String, String>,
+}
+
+pub struct TileMap {
+    map: Map,
+    // tile layers to draw, bottom first
+    draw_layers: Vec<String>,
+    // the size tile images are drawn at
+    image_size: Vec2,
+    pub grid: Grid,
+    /// the size of the whole map in world units
+    pub size: Vec2,
+    /// solid areas in world units from collision objects
+    pub solids: Vec<Rect>,
+    // tiles with something on a collision layer, row by row
+    solid_tiles: Vec<bool>,
+    pub objects: Vec<MapObject>,
+}
+
+impl TileMap {
+    pub fn load(path: &Path, assets: &GlobalAssets) -> Result<Self, String> {
+        let tmx = match path.extension().and_then(|e| e.to_str()) {
+            Some("json" | "tmj") => false,
+            Some("tmx") => true,
+            _ => return Err(format!("{} is not a Tiled map", path.display())),
+        };
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("Failed to read map {}: {}", path.display(), e)),
+        };
+        if tmx {
+            Self::from_tmx(&text, assets)
+        } else {
+            Self::from_json(&text, assets)
+        }
+    }
+
+    /// Load a map from Tiled's JSON format. Tileset images are matched to loaded textures by file name.
+    pub fn from_json(json: &str, assets: &GlobalAssets) -> Result<Self, String> {
+        match serde_json::from_str::<Value>(json) {
+            Ok(raw) => Self::from_tiled(raw, assets),
+            Err(e) => Err(format!("Map is not valid JSON: {}", e)),
+        }
+    }
+
+    /// Load a map from Tiled's .tmx format, by converting it to the JSON Tiled would export
+    pub fn from_tmx(xml: &str, assets: &GlobalAssets) -> Result<Self, String> {
+        match tmx::to_json(xml) {
+            Ok(raw) => Self::from_tiled(raw, assets),
+            Err(e) => Err(format!("Map is not a valid .tmx file: {}", e)),
+        }
+    }
+
+    fn from_tiled(mut raw: Value, assets: &GlobalAssets) -> Result<Self, String> {
+        if raw["infinite"].as_bool() == Some(true) {
+            return Err("Infinite maps are not supported, set a fixed map size in Tiled".to_string());
+        }
+        normalize(&mut raw);
+
+        let mut textures = Vec::new();
+        for tileset in raw["tilesets"].as_array().into_iter().flatten() {
+            if tileset["source"].is_string() {
+                return Err("External tilesets are not supported, embed the tileset in the map".to_string());
+            }
+            let image = tileset["image"].as_str().unwrap_or_default().to_string();
+            let Some(texture) = assets.tileset_texture(&image) else {
+                return Err(format!("No texture loaded for tileset image `{}`", image));
+            };
+            textures.push((image, texture));
+        }
+        let textures = textures.iter().map(|(name, t)| (name.as_str(), t.clone())).collect::<Vec<_>>();
+        Self::build(&raw, &textures)
+    }
+
+    /// An empty orthogonal map of `width` by `height` 16px tiles, with nothing to draw
+    #[cfg(test)]
+    pub fn empty(width: u32, height: u32) -> Self {
+        let raw = serde_json::json!({
+            "orientation": "orthogonal",
+            "width": width,
+            "height": height,
+            "tilewidth": 16,
+            "tileheight": 16,
+            "infinite": false,
+            "layers": [],
+            "tilesets": [],
+        });
+        Self::build(&raw, &[]).expect("an empty map should always load")
+    }
+
+    fn build(raw: &Value, textures: &[(&str, Texture2D)]) -> Result<Self, String> {
+        let map = match macroquad_tiled::load_map(&raw.to_string(), textures, &[]) {
+            Ok(m) => m,
+            Err(e) => return Err(format!("Failed to load map: {}", e)),
+        };
+
+        let projection = match map.raw_tiled_map.orientation.as_str() {
+            "orthogonal" => Projection::Orthogonal,
+            "isometric" => Projection::Isometric,
+            other => return Err(format!("{} maps are not supported, use orthogonal or isometric", other)),
+        };
+        let tile_pixels = vec2(map.raw_tiled_map.tilewidth as f32, map.raw_tiled_map.tileheight as f32);
+        let grid = Grid {
+            projection,
+            tile_size: tile_pixels * MAP_SCALE,
+            width: map.raw_tiled_map.width,
+            height: map.raw_tiled_map.height,
+        };
+        // tile images can be taller than the grid, like walls on an isometric map
+        let image_size = map.raw_tiled_map.tilesets.first()
+            .map(|t| vec2(t.tilewidth as f32, t.tileheight as f32) * MAP_SCALE)
+            .unwrap_or(grid.tile_size);
+
+        let mut draw_layers = Vec::new();
+        let mut solids = Vec::new();
+        let mut solid_tiles = vec![false; (grid.width * grid.height) as usize];
+        let mut objects = Vec::new();
+
+        for layer in &map.raw_tiled_map.layers {
+            match layer.ty.as_str() {
+                "tilelayer" => {
+                    let collision = layer.name.eq_ignore_ascii_case("collision")
+                        || layer.properties.as_ref()
+                            .and_then(|p| p.get("collision"))
+                            .is_some_and(|v| v == "true");
+                    if collision {
+                        for (x, y, tile) in map.tiles(&layer.name, None) {
+                            if tile.is_some() {
+                                solid_tiles[(y * grid.width + x) as usize] = true;
+                            }
+                        }
+                    }
+                    if layer.visible {
+                        draw_layers.push(layer.name.clone());
+                    }
+                }
+                "objectlayer" => {
+                    for object in &layer.objects {
+                        let size = vec2(object.width, object.height) * MAP_SCALE;
+                        let mut pos = match projection {
+                            Projection::Orthogonal => vec2(object.x, object.y) * MAP_SCALE,
+                            // isometric maps measure objects along the grid axes, in tile heights
+                            Projection::Isometric => grid.grid_to_world(vec2(object.x, object.y) / tile_pixels.y),
+                        };
+                        // tile objects are positioned by their bottom left corner, or bottom middle on isometric maps
+                        if object.gid.is_some() {
+                            pos.y -= size.y;
+                            if projection == Projection::Isometric {
+                                pos.x -= size.x / 2.0;
+                            }
+                        }
+                        objects.push(MapObject {
+                            ty: object.ty.to_lowercase(),
+                            name: object.name.clone(),
+                            pos,
+                            size,
+                            properties: object.properties.iter()
+                                .map(|p| (p.name.clone(), p.value.clone()))
+                                .collect(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // collision objects are solid, they don't spawn anything
+        objects.retain(|o| {
+            if o.ty == "collision" {
+                solids.push(Rect::new(o.pos.x, o.pos.y, o.size.x, o.size.y));
+                return false;
+            }
+            true
+        });
+
+        Ok(Self {
+            map,
+            draw_layers,
+            image_size,
+            size: grid.world_size(),
+            grid,
+            solids,
+            solid_tiles,
+            objects,
+        })
+    }
+
+    /// Where the map wants the player to start, if it says. Without a name this is the first spawn point on the map.
+    pub fn spawn_point(&self, name: Option<&str>) -> Option<Vec2> {
+        self.objects.iter()
+            .filter(|o| o.ty == "spawn")
+            .find(|o| name.is_none_or(|n| o.name == n))
+            .map(|o| o.pos)
+    }
+
+    /// Whether any part of `area` is inside a wall
+    pub fn blocks(&self, area: Rect) -> bool {
+        // Rect::overlaps counts touching edges, which would catch the player on a wall they are standing beside
+        let hits_solid = self.solids.iter()
+            .any(|s| area.x < s.right() && area.right() > s.x && area.y < s.bottom() && area.bottom() > s.y);
+        if hits_solid {
+            return true;
+        }
+
+        // checking the corners is enough as long as the area is no bigger than a tile
+        let inset = 0.01;
+        let points = [
+            vec2(area.x + inset, area.y + inset),
+            vec2(area.right() - inset, area.y + inset),
+            vec2(area.x + inset, area.bottom() - inset),
+            vec2(area.right() - inset, area.bottom() - inset),
+            area.center(),
+        ];
+        points.iter().any(|p| match self.grid.tile_at(*p) {
+            Some((x, y)) => self.solid_tiles[(y * self.grid.width + x) as usize],
+            None => false,
+        })
+    }
+
+    /// Draw every visible tile layer, skipping tiles that are off screen
+    pub fn draw(&self, camera: &Camera, backend: &dyn Backend) {
+        let screen = backend.screen_size();
+        // on isometric maps the screen covers a diamond of the grid, so bound all four corners
+        let corners = [Vec2::ZERO, vec2(screen.x, 0.0), vec2(0.0, screen.y), screen]
+            .map(|c| self.grid.world_to_grid(camera.screen_to_world(c, screen)));
+        let min = corners.iter().fold(Vec2::MAX, |a, b| a.min(*b)).floor() - Vec2::ONE;
+        let max = corners.iter().fold(Vec2::MIN, |a, b| a.max(*b)).ceil() + Vec2::ONE;
+        let min_x = (min.x.max(0.0) as u32).min(self.grid.width);
+        let min_y = (min.y.max(0.0) as u32).min(self.grid.height);
+        let max_x = (max.x.max(0.0) as u32).min(self.grid.width);
+        let max_y = (max.y.max(0.0) as u32).min(self.grid.height);
+
+        // a pixel of overlap hides seams between tiles at fractional zoom levels
+        let scale = camera.scale(screen);
+        let dest_size = self.image_size * scale + Vec2::ONE;
+        // images taller than the grid stick up out of their tile
+        let rise = self.image_size.y - self.grid.tile_size.y;
+
+        for layer in &self.draw_layers {
+            // rows then columns is back to front for both projections
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    let Some(tile) = self.map.get_tile(layer, x, y) else {
+                        continue;
+                    };
+                    let origin = self.grid.tile_origin(x, y) - vec2(0.0, rise);
+                    let pos = camera.world_to_screen(origin, screen);
+                    self.map.spr(&tile.tileset, tile.id, Rect::new(pos.x, pos.y, dest_size.x, dest_size.y));
+                }
+            }
+        }
+    }
+}
+
+/// Tiled's JSON doesn't quite match what macroquad-tiled reads, so smooth over the differences:
+/// object layers are called "objectgroup", newer versions use "class" instead of "type",
+/// property values can be numbers or booleans, and layer properties are read as a map.
+/// Lists are only touched if they are there, since indexing a missing key would add it as null, which macroquad-tiled refuses.
+fn normalize(map: &mut Value) {
+    fn list<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+        value.get_mut(key).and_then(Value::as_array_mut).into_iter().flatten()
+    }
+
+    fn stringify_properties(value: &mut Value) {
+        for property in list(value, "properties") {
+            if !property["value"].is_string() {
+                property["value"] = Value::String(property["value"].to_string());
+            }
+        }
+    }
+
+    stringify_properties(map);
+    for tileset in list(map, "tilesets") {
+        stringify_properties(tileset);
+        for tile in list(tileset, "tiles") {
+            stringify_properties(tile);
+        }
+    }
+
+    for layer in list(map, "layers") {
+        if layer["type"] == "objectgroup" {
+            layer["type"] = Value::String("objectlayer".to_string());
+        }
+
+        let properties = layer["properties"].as_array()
+            .map(|list| list.iter()
+                .filter_map(|p| {
+                    let name = p["name"].as_str()?.to_string();
+                    let value = match &p["value"] {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    Some((name, Value::String(value)))
+                })
+                .collect::<serde_json::Map<String, Value>>());
+        if let Some(object) = layer.as_object_mut() {
+            match properties {
+                Some(properties) => { object.insert("properties".to_string(), Value::Object(properties)); }
+                None => { object.remove("properties"); }
+            }
+        }
+
+        for object in list(layer, "objects") {
+            let untyped = object["type"].as_str().is_none_or(|t| t.is_empty());
+            if untyped && object["class"].is_string() {
+                object["type"] = object["class"].clone();
+            }
+            stringify_properties(object);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = r#"<map orientation="isometric" width="3" height="2" tilewidth="32" tileheight="16" infinite="0">
+ <layer id="1" name="Walls" width="3" height="2">
+  <properties><property name="collision" type="bool" value="true"/></properties>
+  <data encoding="csv">1,0,0,0,0,0</data>
+ </layer>
+ <objectgroup id="2" name="Things">
+  <object id="3" name="start" class="spawn" x="16" y="16"/>
+  <object id="4" type="collision" x="0" y="0" width="16" height="16"/>
+ </objectgroup>
+</map>"#;
+
+    #[test]
+    fn tmx_maps_load_like_json_ones() {
+        let mut raw = tmx::to_json(MAP).unwrap();
+        normalize(&mut raw);
+        let map = TileMap::build(&raw, &[]).unwrap();
+
+        assert_eq!((map.grid.projection, map.grid.width, map.grid.height), (Projection::Isometric, 3, 2));
+        assert_eq!(map.draw_layers, vec!["Walls".to_string()]);
+        // without a tileset the tiles themselves don't resolve, but the layer is still read as collision
+        let properties = map.map.raw_tiled_map.layers[0].properties.as_ref().unwrap();
+        assert_eq!(properties.get("collision").map(String::as_str), Some("true"));
+        assert_eq!(map.solids.len(), 1);
+        // isometric objects are measured in tile heights along the grid
+        assert_eq!(map.spawn_point(Some("start")), Some(map.grid.grid_to_world(vec2(1.0, 1.0))));
+    }
+
+    #[test]
+    fn normalize_leaves_missing_lists_out() {
+        let mut raw = serde_json::json!({ "layers": [{ "type": "tilelayer" }], "tilesets": [{}] });
+        normalize(&mut raw);
+        assert!(raw["layers"][0].get("objects").is_none());
+        assert!(raw["tilesets"][0].get("tiles").is_none());
+        assert!(raw.get("properties").is_none());
+    }
+}
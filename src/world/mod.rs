@@ -1,48 +1,93 @@
 use macroquad::color::WHITE;
-use macroquad::math::vec2;
-use macroquad::prelude::{draw_texture_ex, screen_height, screen_width, DrawTextureParams};
+use macroquad::math::{vec2, Rect, Vec2};
+use macroquad::prelude::{draw_texture_ex, DrawTextureParams};
 use macroquad::rand::gen_range;
 use crate::assets::GlobalAssets;
 use crate::controls::Action;
 use crate::error::GameError;
 use crate::gamedata::GameData;
 use crate::ui::tooltip::{tooltip, ToolTipCard};
-use crate::util::{get_sprite_scale, scale_position};
 use crate::world::rock::Rock;
 use crate::world::interactable::Interactable;
+use crate::world::map::{MapObject, TileMap};
+use crate::world::portal::Portal;
 use crate::world::player::{Player, PlayerFacing};
+use crate::warn;
 
 pub mod interactable;
+pub mod inventory;
+pub mod iso;
+pub mod map;
+pub mod portal;
 pub mod rock;
+pub mod rock_type;
 pub mod player;
+pub mod scene;
+pub mod tmx;
 
 pub struct World {
+    /// how big the world is in world units, see `Camera`
+    pub size: Vec2,
+    pub map: TileMap,
     pub player: Player,
     pub interactables: Vec<Box<dyn Interactable>>,
 }
 
 impl World {
-    pub async fn new(assets: &GlobalAssets) -> Result<Self, String> {
-        let player = Player::new().await;
+    pub fn new(assets: &GlobalAssets, map: TileMap) -> Result<Self, String> {
+        let player = Player::new();
         if let Err(e) = player {
             return Err(format!("Failed to initialize player: {}", e));
         }
-        let player = player?;
+        let mut player = player?;
+        let size = map.size;
+        // start where the map says, or in the middle of the world
+        player.pos = map.spawn_point(None).unwrap_or(size / 2.0);
+        player.prev_pos = player.pos;
 
         let mut interactables: Vec<Box<dyn Interactable>> = Vec::new();
-
-        for x in 0..5 {
-            let rock = Rock::new(assets, x, "Rock Pile".to_string(), vec2(gen_range(20.0, screen_width() - 20.0), gen_range(20.0, screen_height() - 20.0)), gen_range(0.0, 360.0));
-
-            interactables.push(Box::new(rock));
+        for object in &map.objects {
+            if let Some(interactable) = spawn_object(assets, object, interactables.len() as u32) {
+                interactables.push(interactable);
+            }
         }
 
         Ok(Self {
+            size,
+            map,
             player,
             interactables,
         })
     }
 
+    /// Put the player at a named spawn point, or the map's first one if it has no spawn point by that name
+    pub fn place_player(&mut self, spawn: Option<&str>) {
+        let pos = self.map.spawn_point(spawn)
+            .or_else(|| {
+                if let Some(name) = spawn {
+                    warn!("No spawn point called `{}`, using the default", name);
+                }
+                self.map.spawn_point(None)
+            })
+            .unwrap_or(self.size / 2.0);
+        self.player.pos = pos;
+        self.player.prev_pos = pos;
+    }
+
+    /// A random spot in the world away from the edges and out of any walls
+    pub fn random_position(&self) -> Vec2 {
+        let mut pos = random_position(self.size);
+        // give up eventually rather than hang on a map that is all wall
+        for _ in 0..32 {
+            let area = Rect::new(pos.x, pos.y, 32.0, 32.0);
+            if !self.map.blocks(area) {
+                break;
+            }
+            pos = random_position(self.size);
+        }
+        pos
+    }
+
     pub fn get_interactable_by_id(&self, id: u32) -> Option<&Box<dyn Interactable>> {
         self.interactables.iter().find(|i| i.get_id() == id)
     }
@@ -65,15 +110,44 @@ impl World {
         self.interactables.push(interactable);
     }
 
-    pub fn draw_player(&self, data: &GameData) {
-        let postion_scale = scale_position(self.player.pos);
+    /// Move everything along by one fixed update lasting `step_seconds`
+    pub fn fixed_update(&mut self, movement: Vec2, player_sprite: Vec2, step_seconds: f32) {
+        self.player.apply_movement(player_sprite, movement, &self.interactables, &self.map, step_seconds, self.size);
+    }
+
+    pub fn draw_map(&self, data: &GameData) {
+        self.map.draw(&data.camera, data.backend.as_ref());
+    }
+
+    /// Draw the player and interactables back to front, so whatever is lower on screen covers what is behind it
+    pub fn draw_entities(&self, data: &GameData) -> Result<(), GameError> {
+        // None is the player
+        let mut order = self.interactables.iter()
+            .map(|i| (i.depth(), Some(i)))
+            .collect::<Vec<_>>();
+        order.push((self.player.depth(data.interpolation), None));
+        // stable, so ties keep the order things were added in
+        order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (_, entity) in order {
+            match entity {
+                Some(interactable) => interactable.draw(data)?,
+                None => self.draw_player(data),
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_player(&self, data: &GameData) {
+        let screen_size = data.backend.screen_size();
+        let postion_scale = data.camera.world_to_screen(self.player.render_pos(data.interpolation), screen_size);
 
         draw_texture_ex(
             &data.assets.player_sprite,
             postion_scale.x, postion_scale.y,
             WHITE,
             DrawTextureParams {
-                dest_size: Some(get_sprite_scale()),
+                dest_size: Some(data.camera.sprite_scale(screen_size)),
                 //dest_size: Some(vec2(32.0, 32.0)),
                 rotation: self.player.rotation,
                 flip_x: self.player.facing == PlayerFacing::UpLeft || self.player.facing == PlayerFacing::DownLeft,
@@ -86,13 +160,21 @@ impl World {
 
     pub fn is_click_on_interactable(&self, data: &GameData) -> Option<u32> {
         for interactable in &self.interactables {
-            if interactable.is_mouse_over(data) && interactable.distance_from_player(data) <= 100.0 {
+            if interactable.is_mouse_over(&data.camera, data.backend.as_ref()) && interactable.distance_from_player(data) <= 100.0 {
                 return Some(interactable.get_id());
             }
         }
         None
     }
 
+    pub fn closest_interactable_in_reach(&self, data: &GameData) -> Option<u32> {
+        self.interactables.iter()
+            .map(|i| (i.get_id(), i.distance_from_player(data)))
+            .filter(|(_, distance)| *distance <= 100.0)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| id)
+    }
+
     pub fn update_interactables(&mut self, delta_time: f32) -> Result<(), GameError> {
         for interactable in &mut self.interactables {
             interactable.update_animation(delta_time)?;
@@ -100,40 +182,187 @@ impl World {
         Ok(())
     }
 
-    pub fn draw_interactables(&self, data: &GameData) -> Result<(), GameError> {
-        for interactable in &self.interactables {
-            interactable.draw(data)?;
-        }
-        Ok(())
-    }
-
     pub fn handle_tooltips(&self, data: &GameData) {
         // if the mouse is on an interactable, give a tooltip
         for interactable in &self.interactables {
-            if interactable.is_mouse_over(data) {
+            if interactable.is_mouse_over(&data.camera, data.backend.as_ref()) {
                 if interactable.distance_from_player(data) <= 100.0 {
-                    let interact_btn = data.control_handler.get_binding(&Action::Interact).unwrap();
+                    let interact_btn = if data.backend.is_gamepad_connected() {
+                        data.control_handler.get_gamepad_binding(&Action::Interact)
+                    } else {
+                        data.control_handler.get_binding(&Action::Interact)
+                    }.unwrap();
                     // let clicks = match interactable.get_attribute("clicks").unwrap_or(InteractableAttribute::UInt(0)) {
                     //     InteractableAttribute::UInt(i) => i,
                     //     _ => 0
                     // };
+                    let mut lines = interactable.tooltip_lines(data);
+                    lines.push(format!("{}Press {}{}{} to interact.", better_term::Color::White,
+                                       better_term::Color::BrightYellow, interact_btn, better_term::Color::White));
+                    //lines.push(format!("{}Clicks: {}{}", better_term::Color::White, better_term::Color::BrightYellow, clicks));
                     let card = ToolTipCard {
                         title: interactable.get_name(),
-                        lines: vec![format!("{}Press {}{}{} to interact.", better_term::Color::White,
-                                            better_term::Color::BrightYellow, interact_btn, better_term::Color::White),
-                                    //format!("{}Clicks: {}{}", better_term::Color::White, better_term::Color::BrightYellow, clicks)
-                        ],
+                        lines,
                     };
-                    tooltip(card, &data.assets);
+                    tooltip(card, &data.assets, data.settings.ui_scale, data.backend.as_ref());
                 } else {
+                    let mut lines = interactable.tooltip_lines(data);
+                    lines.push(format!("{}Get closer to interact!", better_term::Color::White));
                     let card = ToolTipCard {
                         title: interactable.get_name(),
-                        lines: vec![format!("{}Get closer to interact!", better_term::Color::White)],
+                        lines,
                     };
-                    tooltip(card, &data.assets);
+                    tooltip(card, &data.assets, data.settings.ui_scale, data.backend.as_ref());
                 }
             }
         }
     }
 
-}
\ No newline at end of file
+}
+
+/// Turn an object from the map into something in the world, based on its type
+fn spawn_object(assets: &GlobalAssets, object: &MapObject, id: u32) -> Option<Box<dyn Interactable>> {
+    match object.ty.as_str() {
+        "rock" => {
+            let kind = match object.properties.get("kind") {
+                Some(kind) => {
+                    if assets.rocks.get(kind).is_none() {
+                        warn!("Rock `{}` is an unknown kind `{}`", object.name, kind);
+                    }
+                    kind.clone()
+                }
+                None => assets.rocks.random().id.clone(),
+            };
+            let rotation = object.properties.get("rotation")
+                .and_then(|r| r.parse::<f32>().ok())
+                .unwrap_or_else(|| gen_range(0.0, 360.0));
+            Some(Box::new(Rock::new(assets, id, &kind, object.properties.get("name").cloned(), object.pos, rotation)))
+        }
+        "portal" => {
+            let Some(scene) = object.properties.get("scene") else {
+                warn!("Portal `{}` doesn't say which scene it goes to", object.name);
+                return None;
+            };
+            let sprite = object.properties.get("sprite")
+                .and_then(|s| s.parse::<usize>().ok())
+                .unwrap_or(16);
+            Some(Box::new(Portal::new(assets, id, object.name.clone(), object.pos, scene.clone(),
+                                      object.properties.get("spawn").cloned(), sprite)))
+        }
+        // handled by the world itself
+        "spawn" => None,
+        other => {
+            warn!("Unknown map object type `{}` on object `{}`", other, object.name);
+            None
+        }
+    }
+}
+
+fn random_position(size: Vec2) -> Vec2 {
+    vec2(gen_range(20.0, size.x - 20.0), gen_range(20.0, size.y - 20.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::input::KeyCode;
+    use super::*;
+    use crate::backend::{Backend, HeadlessBackend};
+    use crate::controls::{update_held_inputs, ControlHandler};
+    use crate::gamepad::GamepadAxis;
+    use crate::timestep::FIXED_TIMESTEP;
+    use crate::world::player::PLAYER_SPEED;
+
+    fn empty_world() -> World {
+        let map = TileMap::empty(40, 40);
+        let mut player = Player::new().unwrap();
+        player.pos = map.size / 2.0;
+        player.prev_pos = player.pos;
+        World {
+            size: map.size,
+            map,
+            player,
+            interactables: Vec::new(),
+        }
+    }
+
+    /// Run `ticks` frames of gameplay input and fixed updates, one fixed update per frame
+    fn run(world: &mut World, controls: &ControlHandler, backend: &mut HeadlessBackend, ticks: u32) {
+        for _ in 0..ticks {
+            let step = backend.frame_time().as_secs_f32();
+            update_held_inputs(step, controls, backend);
+            let actions = controls.get_actions(backend);
+            let movement = world.player.steer(&actions, controls, backend);
+            world.fixed_update(movement, vec2(16.0, 16.0), step);
+        }
+    }
+
+    #[test]
+    fn holding_keys_walks_the_player() {
+        let mut world = empty_world();
+        let start = world.player.pos;
+        let controls = ControlHandler::default();
+        let mut backend = HeadlessBackend::new(vec2(800.0, 600.0), FIXED_TIMESTEP);
+
+        backend.press_key(KeyCode::S);
+        run(&mut world, &controls, &mut backend, 60);
+
+        assert!((world.player.pos.y - start.y - PLAYER_SPEED).abs() < 0.01);
+        assert_eq!(world.player.pos.x, start.x);
+        assert_eq!(world.player.facing, PlayerFacing::DownRight);
+        assert!((backend.elapsed.as_secs_f32() - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn sprinting_only_lasts_while_held() {
+        let mut world = empty_world();
+        let start = world.player.pos;
+        let controls = ControlHandler::default();
+        let mut backend = HeadlessBackend::new(vec2(800.0, 600.0), FIXED_TIMESTEP);
+
+        backend.press_key(KeyCode::A);
+        backend.press_key(KeyCode::LeftShift);
+        run(&mut world, &controls, &mut backend, 30);
+        assert!(world.player.sprinting);
+        assert!((start.x - world.player.pos.x - PLAYER_SPEED).abs() < 0.01);
+        assert_eq!(world.player.facing, PlayerFacing::UpLeft);
+
+        backend.release_key(KeyCode::LeftShift);
+        run(&mut world, &controls, &mut backend, 30);
+        assert!(!world.player.sprinting);
+        assert!((start.x - world.player.pos.x - PLAYER_SPEED * 1.5).abs() < 0.01);
+
+        backend.release_key(KeyCode::A);
+        run(&mut world, &controls, &mut backend, 10);
+        assert_eq!(world.player.pos, world.player.prev_pos);
+    }
+
+    #[test]
+    fn half_tilted_stick_walks_at_half_speed() {
+        let mut world = empty_world();
+        let start = world.player.pos;
+        let controls = ControlHandler::default();
+        let mut backend = HeadlessBackend::new(vec2(800.0, 600.0), FIXED_TIMESTEP);
+
+        // halfway between the default 0.2 deadzone and full tilt
+        backend.set_gamepad_axis(GamepadAxis::LeftStickX, 0.6);
+        run(&mut world, &controls, &mut backend, 60);
+        assert!((world.player.pos.x - start.x - PLAYER_SPEED * 0.5).abs() < 0.01);
+
+        // resting inside the deadzone doesn't move at all
+        backend.set_gamepad_axis(GamepadAxis::LeftStickX, 0.1);
+        run(&mut world, &controls, &mut backend, 10);
+        assert_eq!(world.player.pos, world.player.prev_pos);
+    }
+
+    #[test]
+    fn walls_stop_the_player() {
+        let mut world = empty_world();
+        let controls = ControlHandler::default();
+        let mut backend = HeadlessBackend::new(vec2(800.0, 600.0), FIXED_TIMESTEP);
+
+        // walk into the top edge of the world for long enough to cross it twice over
+        backend.press_key(KeyCode::W);
+        run(&mut world, &controls, &mut backend, 60 * 12);
+        assert_eq!(world.player.pos.y, 0.0);
+    }
+}
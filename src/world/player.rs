@@ -1,13 +1,22 @@
 use std::f32::consts::PI;
-use macroquad::input::mouse_position;
-use macroquad::math::{vec2, Vec2};
-use macroquad::window::{screen_height, screen_width};
+use macroquad::math::{vec2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+use crate::backend::Backend;
+use crate::camera::{Camera, BASE_SPRITE_SIZE};
+use crate::controls::{Action, ControlHandler};
+use crate::item::{ItemRegistry, ToolStats, FISTS};
 use crate::world::interactable::Interactable;
+use crate::world::inventory::Inventory;
+use crate::world::map::TileMap;
 
-pub const PLAYER_SPEED: f32 = 5.0;
+/// Base window pixels per second
+pub const PLAYER_SPEED: f32 = 120.0;
 const PLAYER_SCALE: (f32, f32) = (16.0, 16.0);
+/// The part of the player that bumps into walls, their feet, relative to `pos`
+const FEET_OFFSET: (f32, f32) = (8.0, 16.0);
+const FEET_SIZE: (f32, f32) = (16.0, 16.0);
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PlayerFacing {
     UpRight,
     UpLeft,
@@ -18,29 +27,111 @@ pub enum PlayerFacing {
 #[derive(Clone)]
 pub struct Player {
     pub pos: Vec2,
+    // where the player was before the last fixed update, drawing blends between this and `pos`
+    pub prev_pos: Vec2,
     pub rotation: f32,
     pub sprinting: bool,
     pub facing: PlayerFacing,
+    pub inventory: Inventory,
+    // the id of the tool item being mined with, bare hands if none
+    pub tool: Option<String>,
 }
 
 impl Player {
     
-    pub async fn new() -> Result<Self, String> {
+    pub fn new() -> Result<Self, String> {
         Ok(Self {
             pos: vec2(0.0, 0.0),
+            prev_pos: vec2(0.0, 0.0),
             rotation: 0.0,
             sprinting: false,
             facing: PlayerFacing::UpRight,
+            inventory: Inventory::default(),
+            tool: None,
         })
     }
 
-    pub fn apply_movement(&mut self, player_sprite_size: Vec2, mut movement: Vec2, interactables: &Vec<Box<dyn Interactable>>, delta_time: u128) {
-        if delta_time == 0 {
-            return;
+    /// The colored name and stats of the equipped tool. It only counts while it's in the inventory,
+    /// otherwise the player mines with their fists.
+    pub fn tool_stats(&self, items: &ItemRegistry) -> (String, ToolStats) {
+        let equipped = self.tool.as_deref()
+            .filter(|id| self.inventory.count(id) > 0)
+            .and_then(|id| items.get(id))
+            .and_then(|def| def.tool.clone().map(|tool| (def.display_name(), tool)));
+        equipped.unwrap_or_else(|| (format!("{}Fists", better_term::Color::White), FISTS))
+    }
+
+    /// Turn this frame's actions into the direction to walk in, facing that way and starting or stopping a sprint
+    pub fn steer(&mut self, actions: &[Action], controls: &ControlHandler, backend: &dyn Backend) -> Vec2 {
+        let sprinting_toggled = controls.is_sprint_toggle();
+        if !sprinting_toggled {
+            // if sprinting is not in toggle mode, it will be set to true each frame only if the button is pressed
+            // this is a limitation of Banana Engine that should be addressed in the future, possibly with a control handler rework
+            self.sprinting = false;
         }
-        if movement.length() > 0.0 {
-            movement = movement.normalize() * (PLAYER_SPEED / delta_time as f32);
+
+        let mut movement = vec2(0.0, 0.0);
+        // handle various movement types
+        for action in actions {
+            match action {
+                Action::MoveUp => {
+                    movement.y -= controls.action_strength(&Action::MoveUp, backend);
+                    self.facing = match self.facing {
+                        PlayerFacing::UpRight => PlayerFacing::UpRight,
+                        PlayerFacing::UpLeft => PlayerFacing::UpLeft,
+                        PlayerFacing::DownLeft => PlayerFacing::UpLeft,
+                        PlayerFacing::DownRight => PlayerFacing::UpRight,
+                    };
+                }
+                Action::MoveDown => {
+                    movement.y += controls.action_strength(&Action::MoveDown, backend);
+                    self.facing = match self.facing {
+                        PlayerFacing::UpRight => PlayerFacing::DownRight,
+                        PlayerFacing::UpLeft => PlayerFacing::DownLeft,
+                        PlayerFacing::DownLeft => PlayerFacing::DownLeft,
+                        PlayerFacing::DownRight => PlayerFacing::DownRight,
+                    };
+                }
+                Action::MoveLeft => {
+                    movement.x -= controls.action_strength(&Action::MoveLeft, backend);
+                    self.facing = match self.facing {
+                        PlayerFacing::UpRight => PlayerFacing::UpLeft,
+                        PlayerFacing::UpLeft => PlayerFacing::UpLeft,
+                        PlayerFacing::DownLeft => PlayerFacing::DownLeft,
+                        PlayerFacing::DownRight => PlayerFacing::DownLeft,
+                    };
+                }
+                Action::MoveRight => {
+                    movement.x += controls.action_strength(&Action::MoveRight, backend);
+                    self.facing = match self.facing {
+                        PlayerFacing::UpRight => PlayerFacing::UpRight,
+                        PlayerFacing::UpLeft => PlayerFacing::UpRight,
+                        PlayerFacing::DownLeft => PlayerFacing::DownRight,
+                        PlayerFacing::DownRight => PlayerFacing::DownRight,
+                    };
+                }
+                Action::Sprint => {
+                    if sprinting_toggled {
+                        self.sprinting = !self.sprinting;
+                    } else {
+                        self.sprinting = true;
+                    }
+                }
+                _ => {}
+            }
         }
+        movement
+    }
+
+    /// Move the player for one fixed update lasting `step_seconds`
+    pub fn apply_movement(&mut self, player_sprite_size: Vec2, mut movement: Vec2, interactables: &Vec<Box<dyn Interactable>>, map: &TileMap, step_seconds: f32, bounds: Vec2) {
+        self.prev_pos = self.pos;
+
+        // analog sticks can ask for less than full speed, but diagonals shouldn't be faster than straight lines
+        if movement.length() > 1.0 {
+            movement = movement.normalize();
+        }
+        movement *= PLAYER_SPEED * step_seconds;
 
         // sprinting
         if self.sprinting {
@@ -91,47 +182,117 @@ impl Player {
             }
         }
 
-        self.pos += movement;
+        // move one axis at a time against the map so the player slides along walls instead of sticking
+        self.pos.x += movement.x;
+        if map.blocks(self.feet()) {
+            self.pos.x -= movement.x;
+        }
+        self.pos.y += movement.y;
+        if map.blocks(self.feet()) {
+            self.pos.y -= movement.y;
+        }
 
-        // hard cap position at window bounds
+        // hard cap position at the edge of the world
         if self.pos.x < 0.0 {
             self.pos.x = 0.0;
-        } else if self.pos.x > screen_width() - PLAYER_SCALE.0 {
-            self.pos.x = screen_width() - PLAYER_SCALE.0;
+        } else if self.pos.x > bounds.x - PLAYER_SCALE.0 {
+            self.pos.x = bounds.x - PLAYER_SCALE.0;
         }
         if self.pos.y < 0.0 {
             self.pos.y = 0.0;
-        } else if self.pos.y > screen_height() - PLAYER_SCALE.0 {
-            self.pos.y = screen_height() - PLAYER_SCALE.0;
+        } else if self.pos.y > bounds.y - PLAYER_SCALE.0 {
+            self.pos.y = bounds.y - PLAYER_SCALE.0;
         }
 
         // teleport the player to 0,0 if they go out of bounds
-        if self.pos.x < -PLAYER_SCALE.0 || self.pos.x > screen_width() + PLAYER_SCALE.0 ||
-            self.pos.y < -PLAYER_SCALE.0 || self.pos.y > screen_height() + PLAYER_SCALE.0 {
+        if self.pos.x < -PLAYER_SCALE.0 || self.pos.x > bounds.x + PLAYER_SCALE.0 ||
+            self.pos.y < -PLAYER_SCALE.0 || self.pos.y > bounds.y + PLAYER_SCALE.0 {
             self.pos = vec2(0.0, 0.0);
+            self.prev_pos = self.pos;
         }
 
     }
 
-    pub fn look_towards_mouse(&mut self) {
-        let mouse_pos = mouse_position();
+    fn feet(&self) -> Rect {
+        Rect::new(self.pos.x + FEET_OFFSET.0, self.pos.y + FEET_OFFSET.1, FEET_SIZE.0, FEET_SIZE.1)
+    }
+
+    /// The position to draw the player at, `alpha` of the way from the previous fixed update to the latest one
+    pub fn render_pos(&self, alpha: f32) -> Vec2 {
+        self.prev_pos.lerp(self.pos, alpha)
+    }
+
+    /// Where the player's feet are when drawn, for sorting them in front of or behind other things
+    pub fn depth(&self, alpha: f32) -> f32 {
+        self.render_pos(alpha).y + BASE_SPRITE_SIZE
+    }
+
+    pub fn look_towards_mouse(&mut self, camera: &Camera, backend: &dyn Backend) {
+        let mouse_pos = camera.screen_to_world(backend.mouse_position(), backend.screen_size());
 
         let pos = vec2(self.pos.x + PLAYER_SCALE.0 / 2.0, self.pos.y + PLAYER_SCALE.1 / 2.0);
 
-        let dx = mouse_pos.0 - pos.x;
-        let dy = mouse_pos.1 - pos.y;
+        let dx = mouse_pos.x - pos.x;
+        let dy = mouse_pos.y - pos.y;
         
         self.rotation = dy.atan2(dx) + (PI / 2.0);
     }
 
-    pub fn is_on_mouse(&self) -> bool {
-        let mouse_pos = mouse_position();
+    pub fn is_on_mouse(&self, camera: &Camera, backend: &dyn Backend) -> bool {
+        let mouse_pos = camera.screen_to_world(backend.mouse_position(), backend.screen_size());
         let pos = vec2(self.pos.x + PLAYER_SCALE.0 / 2.0, self.pos.y + PLAYER_SCALE.1 / 2.0);
 
-        let dx = mouse_pos.0 - pos.x;
-        let dy = mouse_pos.1 - pos.y;
+        let dx = mouse_pos.x - pos.x;
+        let dy = mouse_pos.y - pos.y;
 
         dx.abs() < PLAYER_SCALE.0 / 2.0 && dy.abs() < PLAYER_SCALE.1 / 2.0
     }
     
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+    use crate::timestep::{FixedTimestep, FIXED_TIMESTEP};
+
+    /// Walk for a series of frames, returning where the player ends up and how fast they were going
+    fn walk(frames: &[f64], movement: Vec2, sprinting: bool) -> (Vec2, Vec2) {
+        let map = TileMap::empty(40, 40);
+        let mut player = Player::new().unwrap();
+        player.pos = map.size / 2.0;
+        player.prev_pos = player.pos;
+        player.sprinting = sprinting;
+
+        let step = FIXED_TIMESTEP.as_secs_f32();
+        let mut timestep = FixedTimestep::default();
+        for seconds in frames {
+            for _ in 0..timestep.advance(Duration::from_secs_f64(*seconds)) {
+                player.apply_movement(vec2(16.0, 16.0), movement, &Vec::new(), &map, step, map.size);
+            }
+        }
+        (player.pos, (player.pos - player.prev_pos) / step)
+    }
+
+    #[test]
+    fn movement_is_independent_of_frame_rate() {
+        let thirty = vec![1.0 / 30.0; 30];
+        let sixty = vec![1.0 / 60.0; 60];
+        let uneven = [0.01, 1.0 / 30.0 - 0.01].repeat(30);
+
+        for (movement, sprinting) in [(vec2(1.0, 0.0), false), (vec2(-1.0, 1.0), true), (vec2(0.0, -0.5), false)] {
+            let expected = walk(&thirty, movement, sprinting);
+            assert_eq!(walk(&sixty, movement, sprinting), expected);
+            assert_eq!(walk(&uneven, movement, sprinting), expected);
+        }
+    }
+
+    #[test]
+    fn walks_at_player_speed() {
+        // one second of walking right
+        let (pos, velocity) = walk(&vec![1.0 / 60.0; 60], vec2(1.0, 0.0), false);
+        let start = TileMap::empty(40, 40).size / 2.0;
+        assert!((pos.x - start.x - PLAYER_SPEED).abs() < 0.01);
+        assert!((velocity.x - PLAYER_SPEED).abs() < 0.01);
+        assert_eq!(pos.y, start.y);
+    }
+}
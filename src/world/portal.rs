@@ -0,0 +1,144 @@
+use macroquad::math::Vec2;
+use macroquad::prelude::vec2;
+use crate::animation::Animator;
+use crate::assets::GlobalAssets;
+use crate::error::GameError;
+use crate::gamedata::GameData;
+use crate::gamestate::GameStateAction;
+use crate::world::interactable::{Interactable, InteractableAttribute};
+
+/// A door, ladder or cart that takes the player to another scene
+#[derive(Clone, Debug)]
+pub struct Portal {
+    pub id: u32,
+    pub name: String,
+    pub pos: Vec2,
+    /// the scene to go to
+    pub scene: String,
+    /// the named spawn point to arrive at, or the scene's first one
+    pub spawn: Option<String>,
+    pub animator: Animator,
+}
+
+impl Portal {
+    pub fn new(assets: &GlobalAssets, id: u32, name: String, pos: Vec2, scene: String, spawn: Option<String>, sprite: usize) -> Self {
+        let mut animator = Animator::new(assets.rock_sprite.clone(), vec2(16.0, 16.0));
+
+        animator.add_animation("idle", sprite, 1, 0.0, false);
+
+        Self {
+            id,
+            name,
+            pos,
+            scene,
+            spawn,
+            animator,
+        }
+    }
+}
+
+impl Interactable for Portal {
+    fn interact(&mut self) -> Result<GameStateAction, GameError> {
+        Ok(GameStateAction::ChangeScene {
+            scene: self.scene.clone(),
+            spawn: self.spawn.clone(),
+        })
+    }
+
+    fn get_type(&self) -> &'static str {
+        "portal"
+    }
+
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_sprite_size(&self) -> Vec2 {
+        self.animator.frame_size
+    }
+
+    fn get_animator(&self) -> &Animator {
+        &self.animator
+    }
+
+    fn update_animation(&mut self, delta_time: f32) -> Result<(), GameError> {
+        self.animator.update(delta_time);
+        Ok(())
+    }
+
+    fn draw(&self, data: &GameData) -> Result<(), GameError> {
+        let screen_size = data.backend.screen_size();
+        self.animator.draw(self.get_screen_pos(&data.camera, screen_size), None, data.camera.sprite_scale(screen_size));
+        Ok(())
+    }
+
+    fn get_pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn get_rotation(&self) -> f32 {
+        0.0
+    }
+
+    fn clone_box(&self) -> Box<dyn Interactable> {
+        Box::new(self.clone())
+    }
+
+    fn distance_from_player(&self, data: &GameData) -> f32 {
+        let player = &data.world.player;
+        let player_sprite = &data.assets.player_sprite;
+        let player_pos = vec2(player.pos.x + player_sprite.width() / 2.0, player.pos.y + player_sprite.height() / 2.0);
+        let sprite = self.get_sprite_size();
+        let portal_pos = vec2(self.pos.x + sprite.x / 2.0, self.pos.y + sprite.y / 2.0);
+        player_pos.distance(portal_pos)
+    }
+
+    fn get_attribute(&self, attribute: &str) -> Option<InteractableAttribute> {
+        match attribute {
+            "scene" => Some(InteractableAttribute::String(self.scene.clone())),
+            "spawn" => self.spawn.clone().map(InteractableAttribute::String),
+            "sprite" => self.animator.animations.get("idle").map(|a| InteractableAttribute::UInt(a.start_frame as u32)),
+            _ => None,
+        }
+    }
+
+    fn set_attribute(&mut self, attribute: &str, value: InteractableAttribute) -> Result<(), String> {
+        match attribute {
+            "scene" => {
+                if let InteractableAttribute::String(v) = value {
+                    self.scene = v;
+                    Ok(())
+                } else {
+                    Err(format!("Invalid type for attribute {}: expected String", attribute))
+                }
+            }
+            "spawn" => {
+                if let InteractableAttribute::String(v) = value {
+                    self.spawn = Some(v);
+                    Ok(())
+                } else {
+                    Err(format!("Invalid type for attribute {}: expected String", attribute))
+                }
+            }
+            "sprite" => {
+                if let InteractableAttribute::UInt(v) = value {
+                    if let Some(animation) = self.animator.animations.get_mut("idle") {
+                        animation.start_frame = v as usize;
+                    }
+                    Ok(())
+                } else {
+                    Err(format!("Invalid type for attribute {}: expected UInt", attribute))
+                }
+            }
+            _ => Err(format!("Unknown attribute: {}", attribute)),
+        }
+    }
+
+    fn attribute_names(&self) -> &'static [&'static str] {
+        &["scene", "spawn", "sprite"]
+    }
+}
@@ -7,11 +7,13 @@ use crate::gamedata::GameData;
 use crate::gamestate::GameStateAction;
 use crate::minigame::mine_rock::MineRock;
 use crate::world::interactable::{Interactable, InteractableAttribute};
+use crate::world::rock_type::RockType;
 
 #[derive(Clone, Debug)]
 pub struct Rock {
     pub id: u32,
     pub name: String,
+    pub rock_type: RockType,
     pub pos: Vec2,
     pub rotation: f32,
     pub clicks: u32,
@@ -19,14 +21,18 @@ pub struct Rock {
 }
 
 impl Rock {
-    pub fn new(assets: &GlobalAssets, id: u32, name: String, pos: Vec2, rotation: f32) -> Self {
+    /// A rock of the kind with this id, named after its kind unless it has a name of its own
+    pub fn new(assets: &GlobalAssets, id: u32, kind: &str, name: Option<String>, pos: Vec2, rotation: f32) -> Self {
+        let rock_type = assets.rocks.get_or_default(kind).clone();
         let mut animator = Animator::new(assets.rock_sprite.clone(), vec2(16.0, 16.0));
 
-        animator.add_animation("mining", 3, 10, 0.0, false);
+        animator.add_animation("mining", rock_type.frames.start, rock_type.frames.count, 0.0, false);
+        animator.tint = rock_type.tint();
 
         Self {
             id,
-            name,
+            name: name.unwrap_or_else(|| rock_type.name.clone()),
+            rock_type,
             pos,
             rotation,
             clicks: 0,
@@ -40,6 +46,10 @@ impl Interactable for Rock {
         Ok(GameStateAction::SpawnOverlay(MineRock::new(self.id)?))
     }
 
+    fn get_type(&self) -> &'static str {
+        "rock"
+    }
+
     fn get_name(&self) -> String {
         self.name.clone()
     }
@@ -53,20 +63,14 @@ impl Interactable for Rock {
     }
 
     fn update_animation(&mut self, _delta_time: f32) -> Result<(), GameError> {
-        // convert clicks to frame, where there are 8 frames and 16 clicks
-        let frame = if self.clicks > 0 {
-            (self.clicks / 2) % 8
-        } else {
-            0
-        };
-
-        self.animator.current_frame = frame as usize;
+        self.animator.current_frame = self.rock_type.crack_frame(self.clicks);
 
         Ok(())
     }
 
-    fn draw(&self, _data: &GameData) -> Result<(), GameError> {
-        self.animator.draw(self.get_scaled_pos(), Some(self.rotation), None);
+    fn draw(&self, data: &GameData) -> Result<(), GameError> {
+        let screen_size = data.backend.screen_size();
+        self.animator.draw(self.get_screen_pos(&data.camera, screen_size), Some(self.rotation), data.camera.sprite_scale(screen_size));
         Ok(())
     }
 
@@ -86,6 +90,7 @@ impl Interactable for Rock {
         Box::new(Self {
             id: self.id.clone(),
             name: self.name.clone(),
+            rock_type: self.rock_type.clone(),
             pos: self.pos.clone(),
             rotation: self.rotation.clone(),
             clicks: self.clicks.clone(),
@@ -107,6 +112,8 @@ impl Interactable for Rock {
     fn get_attribute(&self, attribute: &str) -> Option<InteractableAttribute> {
         match attribute {
             "clicks" => Some(InteractableAttribute::UInt(self.clicks)),
+            "kind" => Some(InteractableAttribute::String(self.rock_type.id.clone())),
+            "durability" => Some(InteractableAttribute::UInt(self.rock_type.durability)),
             _ => None,
         }
     }
@@ -121,7 +128,29 @@ impl Interactable for Rock {
                     Err(format!("Invalid type for attribute {}: expected UInt", attribute))
                 }
             }
+            // the kind is picked when the rock is made, saves pass it to `Rock::new`
+            "kind" => match value {
+                InteractableAttribute::String(_) => Ok(()),
+                _ => Err(format!("Invalid type for attribute {}: expected String", attribute)),
+            },
             _ => Err(format!("Unknown attribute: {}", attribute)),
         }
     }
+
+    fn tooltip_lines(&self, data: &GameData) -> Vec<String> {
+        let mut lines = vec![format!("{}Durability: {}{}/{}", better_term::Color::White, better_term::Color::BrightYellow,
+                                     self.rock_type.durability - self.clicks.min(self.rock_type.durability), self.rock_type.durability)];
+        let (_, tool) = data.world.player.tool_stats(&data.items);
+        if tool.tier < self.rock_type.tool_tier {
+            let needed = data.items.tool_for_tier(self.rock_type.tool_tier)
+                .map(|def| def.display_name())
+                .unwrap_or_else(|| format!("a tier {} tool", self.rock_type.tool_tier));
+            lines.push(format!("{}Requires {}", better_term::Color::BrightRed, needed));
+        }
+        lines
+    }
+
+    fn attribute_names(&self) -> &'static [&'static str] {
+        &["clicks", "kind"]
+    }
 }
\ No newline at end of file
@@ -0,0 +1,160 @@
+use macroquad::color::{Color, WHITE};
+use macroquad::prelude::Texture2D;
+use macroquad::rand::gen_range;
+use serde::Deserialize;
+use crate::item::parse_hex;
+
+/// The kinds of rock that ship with the game
+pub const ROCKS_FILE: &str = include_str!("../../assets/data/rocks.json");
+
+/// The crack animation, as frames of the rock sheet
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RockFrames {
+    /// the untouched rock, the rest crack further and further
+    pub start: usize,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RockType {
+    pub id: String,
+    pub name: String,
+    /// how many hits it takes to break
+    pub durability: u32,
+    pub frames: RockFrames,
+    /// "#rrggbb" the sprite is drawn with, so kinds can share frames
+    #[serde(default)]
+    pub tint: Option<String>,
+    /// the tool tier needed to mine it, 0 is bare hands
+    #[serde(default)]
+    pub tool_tier: u32,
+    /// the loot table it drops from, otherwise whatever rocks drop by default
+    #[serde(default)]
+    pub loot: Option<String>,
+    /// how often it spawns compared to other kinds
+    #[serde(default = "default_spawn_weight")]
+    pub spawn_weight: u32,
+}
+
+fn default_spawn_weight() -> u32 {
+    1
+}
+
+impl RockType {
+    pub fn tint(&self) -> Color {
+        match self.tint.as_deref().and_then(parse_hex) {
+            Some((r, g, b)) => Color::from_rgba(r, g, b, 255),
+            None => WHITE,
+        }
+    }
+
+    /// The frame of the crack animation after some hits, only reaching the last frame as it breaks
+    pub fn crack_frame(&self, clicks: u32) -> usize {
+        let progress = clicks.min(self.durability) as usize * self.frames.count / self.durability as usize;
+        progress.min(self.frames.count - 1)
+    }
+
+    fn validate(&self, sheet: &Texture2D) -> Result<(), String> {
+        if self.id.is_empty() || !self.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            return Err(format!("id `{}` should be lowercase letters, digits and underscores", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err("name is empty".to_string());
+        }
+        if self.durability == 0 {
+            return Err("durability must be at least 1".to_string());
+        }
+        let sheet_frames = (sheet.width() / 16.0) as usize * (sheet.height() / 16.0) as usize;
+        if self.frames.count == 0 || self.frames.start + self.frames.count > sheet_frames {
+            return Err(format!("frames {}..{} aren't on the rock sheet, which has {}",
+                               self.frames.start, self.frames.start + self.frames.count, sheet_frames));
+        }
+        if let Some(tint) = &self.tint {
+            if parse_hex(tint).is_none() {
+                return Err(format!("tint `{}` isn't a #rrggbb hex color", tint));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct RocksFile {
+    rocks: Vec<serde_json::Value>,
+}
+
+/// Every kind of rock, in the order they're listed. Never empty, `from_json` refuses files without a usable rock.
+#[derive(Clone, Debug)]
+pub struct RockTypes {
+    types: Vec<RockType>,
+}
+
+impl RockTypes {
+    /// Parse a rocks file, leaving out broken entries and returning them as errors like items
+    pub fn from_json(json: &str, sheet: &Texture2D) -> Result<(Self, Vec<String>), String> {
+        let file = match serde_json::from_str::<RocksFile>(json) {
+            Ok(f) => f,
+            Err(e) => return Err(format!("Rocks file is invalid: {}", e)),
+        };
+
+        let mut rocks = Self { types: Vec::new() };
+        let mut errors = Vec::new();
+        for (i, entry) in file.rocks.into_iter().enumerate() {
+            let label = match entry.get("id").and_then(|id| id.as_str()) {
+                Some(id) => format!("Rock {} (`{}`)", i, id),
+                None => format!("Rock {}", i),
+            };
+            let rock = match serde_json::from_value::<RockType>(entry) {
+                Ok(r) => r,
+                Err(e) => {
+                    errors.push(format!("{}: {}", label, e));
+                    continue;
+                }
+            };
+            if let Err(e) = rock.validate(sheet) {
+                errors.push(format!("{}: {}", label, e));
+                continue;
+            }
+            if rocks.get(&rock.id).is_some() {
+                errors.push(format!("{}: id is already used by an earlier rock", label));
+                continue;
+            }
+            rocks.types.push(rock);
+        }
+        if rocks.types.is_empty() {
+            return Err("Rocks file has no usable rocks".to_string());
+        }
+        Ok((rocks, errors))
+    }
+
+    pub fn get(&self, id: &str) -> Option<&RockType> {
+        self.types.iter().find(|r| r.id == id)
+    }
+
+    /// A kind of rock by id, or the first one for ids that don't exist anymore
+    pub fn get_or_default(&self, id: &str) -> &RockType {
+        self.get(id).unwrap_or(&self.types[0])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RockType> {
+        self.types.iter()
+    }
+
+    /// Pick a kind by spawn weight, with macroquad's generator so replays spawn the same rocks
+    pub fn random(&self) -> &RockType {
+        let total: u32 = self.types.iter().map(|r| r.spawn_weight).sum();
+        if total == 0 {
+            return &self.types[0];
+        }
+        let mut pick = gen_range(0, total);
+        for rock in &self.types {
+            if pick < rock.spawn_weight {
+                return rock;
+            }
+            pick -= rock.spawn_weight;
+        }
+        &self.types[0]
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::assets::GlobalAssets;
+use crate::world::map::TileMap;
+use crate::world::World;
+
+/// An area of the game with its own map
+pub struct Scene {
+    pub id: &'static str,
+    pub name: &'static str,
+    map: &'static str,
+}
+
+pub const SCENES: [Scene; 3] = [
+    Scene { id: "jungle", name: "The Jungle", map: include_str!("../../assets/maps/jungle.json") },
+    Scene { id: "lobby", name: "A.P.E. Inc Lobby", map: include_str!("../../assets/maps/lobby.json") },
+    Scene { id: "mine", name: "The Mine", map: include_str!("../../assets/maps/mine.json") },
+];
+
+/// Where a new game starts
+pub const START_SCENE: &str = "jungle";
+
+/// Seconds to fade out, and then the same again to fade back in
+const FADE_TIME: f32 = 0.35;
+
+pub fn get_scene(id: &str) -> Option<&'static Scene> {
+    SCENES.iter().find(|s| s.id == id)
+}
+
+/// Build a fresh world for a scene, as if it had never been visited
+pub fn load_scene(id: &str, assets: &GlobalAssets) -> Result<World, String> {
+    let Some(scene) = get_scene(id) else {
+        return Err(format!("No scene called `{}`", id));
+    };
+    let map = match TileMap::from_json(scene.map, assets) {
+        Ok(m) => m,
+        Err(e) => return Err(format!("Failed to load map for {}: {}", scene.name, e)),
+    };
+    World::new(assets, map)
+}
+
+/// The scene to start in and its world. `--map <file>` starts in a map from disk instead,
+/// and `--scene <id>` starts in another built in scene.
+pub fn start_from_args(assets: &GlobalAssets) -> Result<(String, World), String> {
+    let args = std::env::args().collect::<Vec<String>>();
+    let arg_after = |flag: &str| args.iter()
+        .position(|a| a == flag)
+        .map(|i| args.get(i + 1).cloned().ok_or(format!("{} needs a value", flag)));
+
+    if let Some(path) = arg_after("--map") {
+        let path = path?;
+        let path = Path::new(&path);
+        let map = TileMap::load(path, assets)?;
+        // maps from disk are named after their file so portals elsewhere can't clash with them
+        let id = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or(path.display().to_string());
+        return Ok((id, World::new(assets, map)?));
+    }
+    let id = match arg_after("--scene") {
+        Some(id) => id?,
+        None => START_SCENE.to_string(),
+    };
+    let world = load_scene(&id, assets)?;
+    Ok((id, world))
+}
+
+struct Transition {
+    scene: String,
+    spawn: Option<String>,
+    // counts up through the fade out and then the fade in
+    timer: f32,
+    swapped: bool,
+}
+
+/// Keeps track of which scene is active, the worlds of scenes that have been left,
+/// and the fade between them. The active scene's world lives in `GameData::world`.
+pub struct SceneManager {
+    pub current: String,
+    // scenes that have been visited, kept as they were left
+    visited: HashMap<String, World>,
+    transition: Option<Transition>,
+}
+
+impl SceneManager {
+    pub fn new(current: String) -> Self {
+        Self {
+            current,
+            visited: HashMap::new(),
+            transition: None,
+        }
+    }
+
+    /// Start fading out towards another scene. Does nothing if already on the way somewhere.
+    pub fn travel(&mut self, scene: String, spawn: Option<String>) {
+        if self.transition.is_some() {
+            return;
+        }
+        self.transition = Some(Transition {
+            scene,
+            spawn,
+            timer: 0.0,
+            swapped: false,
+        });
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// How much the screen is faded to black, from 0.0 to 1.0
+    pub fn fade(&self) -> f32 {
+        match &self.transition {
+            Some(t) if !t.swapped => (t.timer / FADE_TIME).min(1.0),
+            Some(t) => (1.0 - (t.timer - FADE_TIME) / FADE_TIME).max(0.0),
+            None => 0.0,
+        }
+    }
+
+    /// Advance the fade. Returns the scene and spawn point to switch to once the screen is fully black.
+    pub fn update(&mut self, delta_seconds: f32) -> Option<(String, Option<String>)> {
+        let transition = self.transition.as_mut()?;
+        transition.timer += delta_seconds;
+        if !transition.swapped {
+            if transition.timer < FADE_TIME {
+                return None;
+            }
+            transition.swapped = true;
+            return Some((transition.scene.clone(), transition.spawn.clone()));
+        }
+        if transition.timer >= FADE_TIME * 2.0 {
+            self.transition = None;
+        }
+        None
+    }
+
+    /// Give up on the current transition and fade straight back in
+    pub fn cancel(&mut self) {
+        if let Some(transition) = &mut self.transition {
+            transition.swapped = true;
+            transition.timer = transition.timer.max(FADE_TIME);
+        }
+    }
+
+    /// Take a visited scene's world back out to make it active again
+    pub fn take_visited(&mut self, id: &str) -> Option<World> {
+        self.visited.remove(id)
+    }
+
+    /// Every scene that has been left, and its world as it was left
+    pub fn visited(&self) -> impl Iterator<Item = (&String, &World)> {
+        self.visited.iter()
+    }
+
+    /// Keep a scene's world so it is the same when the player comes back
+    pub fn store_visited(&mut self, id: String, world: World) {
+        self.visited.insert(id, world);
+    }
+}
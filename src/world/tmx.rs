@@ -0,0 +1,444 @@
+use serde_json::{json, Map, Value};
+
+/*
+Tiled's own .tmx format, read just far enough to turn it into the same JSON that Tiled exports,
+so both go through `TileMap::from_tiled`. Only what the JSON path uses is converted:
+the map's size and orientation, embedded tilesets, tile layers saved as CSV or XML,
+object layers, groups (flattened into their layers) and properties.
+*/
+
+/// An XML element with its attributes, child elements and the text directly inside it
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    fn int(&self, name: &str, default: i64) -> Result<i64, String> {
+        match self.attr(name) {
+            Some(v) => v.trim().parse().map_err(|_| format!("`{}` on <{}> is not a whole number: {}", name, self.name, v)),
+            None => Ok(default),
+        }
+    }
+
+    fn float(&self, name: &str, default: f64) -> Result<f64, String> {
+        match self.attr(name) {
+            Some(v) => v.trim().parse().map_err(|_| format!("`{}` on <{}> is not a number: {}", name, self.name, v)),
+            None => Ok(default),
+        }
+    }
+
+    /// Tiled leaves `visible` out unless it's hidden
+    fn visible(&self) -> bool {
+        self.attr("visible") != Some("0")
+    }
+}
+
+/// Reads the subset of XML that Tiled writes: elements, attributes, text, comments and CDATA.
+struct Parser<'a> {
+    xml: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.xml[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Move past the next `end`, returning everything before it
+    fn take_until(&mut self, end: &str) -> Result<&'a str, String> {
+        let Some(index) = self.rest().find(end) else {
+            return Err(format!("Expected `{}` before the end of the file", end));
+        };
+        let taken = &self.rest()[..index];
+        self.pos += index + end.len();
+        Ok(taken)
+    }
+
+    fn take_name(&mut self) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '=')).unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), String> {
+        if !self.rest().starts_with(text) {
+            let found = self.rest().chars().take(20).collect::<String>();
+            return Err(format!("Expected `{}` but found `{}`", text, found));
+        }
+        self.pos += text.len();
+        Ok(())
+    }
+
+    /// The root element, skipping the declaration, comments and doctype before it
+    fn document(&mut self) -> Result<Element, String> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.take_until("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.take_until("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.take_until(">")?;
+            } else {
+                return self.element();
+            }
+        }
+    }
+
+    fn element(&mut self) -> Result<Element, String> {
+        self.expect("<")?;
+        let mut element = Element {
+            name: self.take_name().to_string(),
+            ..Default::default()
+        };
+        if element.name.is_empty() {
+            return Err("Found a tag without a name".to_string());
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let name = self.take_name().to_string();
+            if name.is_empty() {
+                return Err(format!("Couldn't read the attributes of <{}>", element.name));
+            }
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = if self.rest().starts_with('\'') { "'" } else { "\"" };
+            self.expect(quote)?;
+            let value = unescape(self.take_until(quote)?);
+            element.attributes.push((name, value));
+        }
+
+        loop {
+            if self.rest().starts_with("</") {
+                self.pos += 2;
+                let name = self.take_name();
+                if name != element.name {
+                    return Err(format!("<{}> was closed by </{}>", element.name, name));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else if self.rest().starts_with("<!--") {
+                self.take_until("-->")?;
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                element.text.push_str(self.take_until("]]>")?);
+            } else if self.rest().starts_with('<') {
+                element.children.push(self.element()?);
+            } else if self.rest().is_empty() {
+                return Err(format!("<{}> is never closed", element.name));
+            } else {
+                let end = self.rest().find('<').unwrap_or(self.rest().len());
+                element.text.push_str(&unescape(&self.rest()[..end]));
+                self.pos += end;
+            }
+        }
+    }
+}
+
+/// Replace XML's character references with the characters they stand for
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let reference = &rest[1..end];
+        let replacement = match reference {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => reference.strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| reference.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match replacement {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            // not a reference we know, keep it as written
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Convert a .tmx map to the JSON Tiled would have exported for it
+pub fn to_json(xml: &str) -> Result<Value, String> {
+    let map = Parser { xml, pos: 0 }.document()?;
+    if map.name != "map" {
+        return Err(format!("Expected a <map> but the file starts with <{}>", map.name));
+    }
+
+    let mut tilesets = Vec::new();
+    for tileset in map.children("tileset") {
+        tilesets.push(convert_tileset(tileset)?);
+    }
+    let mut layers = Vec::new();
+    convert_layers(&map, true, &mut layers)?;
+
+    Ok(json!({
+        "orientation": map.attr("orientation").unwrap_or("orthogonal"),
+        "renderorder": map.attr("renderorder").unwrap_or("right-down"),
+        "width": map.int("width", 0)?,
+        "height": map.int("height", 0)?,
+        "tilewidth": map.int("tilewidth", 0)?,
+        "tileheight": map.int("tileheight", 0)?,
+        "infinite": map.attr("infinite") == Some("1"),
+        "properties": convert_properties(&map),
+        "layers": layers,
+        "tilesets": tilesets,
+    }))
+}
+
+fn convert_tileset(tileset: &Element) -> Result<Value, String> {
+    let firstgid = tileset.int("firstgid", 1)?;
+    // left for `TileMap::from_tiled` to turn down with the same message as JSON maps
+    if let Some(source) = tileset.attr("source") {
+        return Ok(json!({ "firstgid": firstgid, "source": source }));
+    }
+    let image = tileset.child("image");
+    let mut tiles = Vec::new();
+    for tile in tileset.children("tile") {
+        let mut converted = json!({ "id": tile.int("id", 0)?, "properties": convert_properties(tile) });
+        if let Some(ty) = tile.attr("type").or(tile.attr("class")) {
+            converted["type"] = Value::from(ty);
+        }
+        tiles.push(converted);
+    }
+    Ok(json!({
+        "firstgid": firstgid,
+        "name": tileset.attr("name").unwrap_or_default(),
+        "tilewidth": tileset.int("tilewidth", 0)?,
+        "tileheight": tileset.int("tileheight", 0)?,
+        "tilecount": tileset.int("tilecount", 0)?,
+        "columns": tileset.int("columns", 0)?,
+        "margin": tileset.int("margin", 0)?,
+        "spacing": tileset.int("spacing", 0)?,
+        "image": image.and_then(|i| i.attr("source")).unwrap_or_default(),
+        "imagewidth": image.map_or(Ok(0), |i| i.int("width", 0))?,
+        "imageheight": image.map_or(Ok(0), |i| i.int("height", 0))?,
+        "properties": convert_properties(tileset),
+        "tiles": tiles,
+    }))
+}
+
+/// Layers in the order they're drawn. Groups are flattened, and hiding a group hides everything in it.
+fn convert_layers(parent: &Element, visible: bool, layers: &mut Vec<Value>) -> Result<(), String> {
+    for element in &parent.children {
+        let visible = visible && element.visible();
+        match element.name.as_str() {
+            "layer" => layers.push(convert_tile_layer(element, visible)?),
+            "objectgroup" => layers.push(convert_object_layer(element, visible)?),
+            "group" => convert_layers(element, visible, layers)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn convert_tile_layer(layer: &Element, visible: bool) -> Result<Value, String> {
+    let name = layer.attr("name").unwrap_or_default();
+    let Some(data) = layer.child("data") else {
+        return Err(format!("Layer `{}` has no tile data", name));
+    };
+    let tiles = match data.attr("encoding") {
+        Some("csv") => data.text.split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| gid.parse::<u32>().map_err(|_| format!("Layer `{}` has a tile that isn't a number: {}", name, gid)))
+            .collect::<Result<Vec<u32>, String>>()?,
+        None => data.children("tile")
+            .map(|tile| tile.int("gid", 0).map(|gid| gid as u32))
+            .collect::<Result<Vec<u32>, String>>()?,
+        Some(other) => return Err(format!(
+            "Layer `{}` is saved as {}, set Tile Layer Format to CSV in the map's properties in Tiled", name, other)),
+    };
+    Ok(json!({
+        "type": "tilelayer",
+        "name": name,
+        "width": layer.int("width", 0)?,
+        "height": layer.int("height", 0)?,
+        "opacity": layer.float("opacity", 1.0)?,
+        "visible": visible,
+        "x": 0,
+        "y": 0,
+        "data": tiles,
+        "properties": convert_properties(layer),
+    }))
+}
+
+fn convert_object_layer(layer: &Element, visible: bool) -> Result<Value, String> {
+    let mut objects = Vec::new();
+    for object in layer.children("object") {
+        let mut converted = json!({
+            "id": object.int("id", 0)?,
+            "name": object.attr("name").unwrap_or_default(),
+            "type": object.attr("type").unwrap_or_default(),
+            "x": object.float("x", 0.0)?,
+            "y": object.float("y", 0.0)?,
+            "width": object.float("width", 0.0)?,
+            "height": object.float("height", 0.0)?,
+            "rotation": object.float("rotation", 0.0)?,
+            "visible": object.visible(),
+            "properties": convert_properties(object),
+        });
+        if let Some(class) = object.attr("class") {
+            converted["class"] = Value::from(class);
+        }
+        if object.attr("gid").is_some() {
+            converted["gid"] = Value::from(object.int("gid", 0)?);
+        }
+        objects.push(converted);
+    }
+    Ok(json!({
+        "type": "objectgroup",
+        "name": layer.attr("name").unwrap_or_default(),
+        "opacity": layer.float("opacity", 1.0)?,
+        "visible": visible,
+        "objects": objects,
+        "properties": convert_properties(layer),
+    }))
+}
+
+/// Properties as Tiled's JSON lists them. Values are kept as text, the same as `normalize` leaves them.
+fn convert_properties(element: &Element) -> Value {
+    let properties = element.child("properties")
+        .into_iter()
+        .flat_map(|p| p.children("property"))
+        .map(|property| {
+            let mut converted = Map::new();
+            converted.insert("name".to_string(), Value::from(property.attr("name").unwrap_or_default()));
+            converted.insert("type".to_string(), Value::from(property.attr("type").unwrap_or("string")));
+            // multi-line strings are written as the element's text instead of an attribute
+            let value = property.attr("value").map(str::to_string).unwrap_or_else(|| property.text.clone());
+            converted.insert("value".to_string(), Value::String(value));
+            Value::Object(converted)
+        })
+        .collect::<Vec<Value>>();
+    Value::Array(properties)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- made in Tiled -->
+<map version="1.10" orientation="isometric" renderorder="right-down" width="3" height="2" tilewidth="32" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+  <image source="../textures/tiles.png" width="64" height="64"/>
+ </tileset>
+ <layer id="1" name="Floor" width="3" height="2">
+  <data encoding="csv">
+1,2,0,
+4,3,1
+</data>
+ </layer>
+ <group id="2" name="Hidden" visible="0">
+  <layer id="3" name="Walls" width="3" height="2">
+   <properties>
+    <property name="collision" type="bool" value="true"/>
+   </properties>
+   <data>
+    <tile gid="2"/><tile/><tile/><tile/><tile/><tile gid="1"/>
+   </data>
+  </layer>
+ </group>
+ <objectgroup id="4" name="Things">
+  <object id="5" name="Door &amp; Hall" class="portal" x="16" y="32.5" width="16" height="16">
+   <properties>
+    <property name="scene" value="mine"/>
+    <property name="note">two
+lines</property>
+   </properties>
+  </object>
+  <object id="6" type="rock" gid="3" x="48" y="16" width="32" height="32"/>
+ </objectgroup>
+</map>
+"#;
+
+    #[test]
+    fn converts_a_map_to_tiled_json() {
+        let json = to_json(MAP).unwrap();
+        assert_eq!(json["orientation"], "isometric");
+        assert_eq!((json["width"].as_u64(), json["tileheight"].as_u64()), (Some(3), Some(16)));
+        assert_eq!(json["infinite"], false);
+        assert_eq!(json["tilesets"][0]["image"], "../textures/tiles.png");
+        assert_eq!(json["tilesets"][0]["imagewidth"], 64);
+
+        let layers = json["layers"].as_array().unwrap();
+        assert_eq!(layers.len(), 3);
+        assert_eq!(layers[0]["data"], json!([1, 2, 0, 4, 3, 1]));
+        assert_eq!(layers[0]["visible"], true);
+        // layers in a hidden group are hidden, and XML tiles without a gid are empty
+        assert_eq!(layers[1]["visible"], false);
+        assert_eq!(layers[1]["data"], json!([2, 0, 0, 0, 0, 1]));
+        assert_eq!(layers[1]["properties"][0]["value"], "true");
+
+        let objects = layers[2]["objects"].as_array().unwrap();
+        assert_eq!(layers[2]["type"], "objectgroup");
+        assert_eq!(objects[0]["name"], "Door & Hall");
+        assert_eq!(objects[0]["class"], "portal");
+        assert_eq!(objects[0]["y"], 32.5);
+        assert_eq!(objects[0]["properties"][1]["value"], "two\nlines");
+        assert!(objects[0].get("gid").is_none());
+        assert_eq!(objects[1]["gid"], 3);
+    }
+
+    #[test]
+    fn refuses_what_it_cant_read() {
+        assert!(to_json("<tileset name=\"tiles\"/>").is_err());
+        assert!(to_json("<map><layer name=\"Floor\"></map>").is_err());
+        let base64 = "<map><layer name=\"Floor\"><data encoding=\"base64\">AQAAAA==</data></layer></map>";
+        assert!(to_json(base64).unwrap_err().contains("CSV"));
+    }
+
+    #[test]
+    fn unescapes_character_references() {
+        assert_eq!(unescape("a &lt;b&gt; &#65;&#x42; &amp;amp; & ;"), "a <b> AB &amp; & ;");
+    }
+}